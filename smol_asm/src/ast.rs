@@ -1,3 +1,4 @@
+#[allow(dead_code)]
 trait Arg {
     fn args(self) -> Vec<RegType>;
     fn try_parse(input: &str) -> Result<Self, String>
@@ -15,16 +16,34 @@ pub struct Arg0 {}
 } */
 
 #[derive(Debug)]
-#[allow(dead_code)]
-struct Arg1<A1: Register> {
-    arg1: A1,
+pub struct Arg1<A1: Register> {
+    pub arg1: A1,
 }
 
-/* impl<A1: Register> Arg for Arg1<A1> {
+impl<A1: Register> Arg for Arg1<A1> {
     fn args(self) -> Vec<RegType> {
         vec![self.arg1.parse()]
     }
-} */
+
+    fn try_parse(input: &str) -> Result<Self, String> {
+        input.try_into()
+    }
+}
+
+impl<A1: Register> TryFrom<&str> for Arg1<A1> {
+    type Error = String;
+
+    fn try_from(line: &str) -> Result<Self, Self::Error> {
+        let items: Vec<&str> = line.split_ascii_whitespace().collect();
+        // This includes the operator
+        if items.len() < 2 {
+            return Err(format!("Exected 1 argument got {}", items.len() - 1));
+        }
+
+        let arg1 = A1::try_parse(items[1])?;
+        Ok(Self { arg1 })
+    }
+}
 
 #[derive(Debug)]
 pub struct Arg2<A1: Register, A2: Register> {
@@ -71,9 +90,18 @@ pub enum R8Regs {
 }
 
 #[derive(Debug)]
+pub enum R16Regs {
+    L0,
+    L1,
+}
+
+#[derive(Debug)]
+#[allow(dead_code)]
 pub enum RegType {
     /// 8-bit register
     R8(R8Regs),
+    /// 16-bit register
+    R16(R16Regs),
     /// 8-bit immediate
     I8(u8),
 }
@@ -136,6 +164,35 @@ impl Register for R8 {
     }
 }
 
+#[derive(Debug)]
+pub struct R16 {
+    pub register: R16Regs,
+}
+
+impl TryFrom<&str> for R16 {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let register = match value.trim() {
+            "l0" => R16Regs::L0,
+            "l1" => R16Regs::L1,
+            _ => return Err(format!("Expected l0-1, received {value}")),
+        };
+
+        Ok(Self { register })
+    }
+}
+
+impl Register for R16 {
+    fn parse(self) -> RegType {
+        RegType::R16(self.register)
+    }
+
+    fn try_parse(input: &str) -> Result<Self, String> {
+        input.try_into()
+    }
+}
+
 #[derive(Debug)]
 pub struct I8 {
     pub value: u8,
@@ -164,6 +221,79 @@ impl Register for I8 {
     }
 }
 
+/// Memory address operand of the load and store instructions
+#[derive(Debug)]
+pub enum Address {
+    /// `sp` or `sp+<0-255>`
+    StackOffset(u8),
+    /// `sp+r<0-7>`
+    StackRegister(R8),
+    /// `l0` or `l1` used as a 16-bit pointer
+    Pointer(R16),
+    /// `<0-65535>`
+    Immediate(u16),
+    /// Name of a variable
+    Variable(String),
+}
+
+impl TryFrom<&str> for Address {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let val = value.trim();
+
+        // Only the exact register forms, variables can start with `sp` or `l`
+        if val == "sp" {
+            return Ok(Self::StackOffset(0));
+        }
+        if let Some(offset) = val.strip_prefix("sp+") {
+            if offset.starts_with('r') {
+                return Ok(Self::StackRegister(R8::try_parse(offset)?));
+            }
+            return Ok(Self::StackOffset(I8::try_parse(offset)?.value));
+        }
+
+        if val == "l0" || val == "l1" {
+            return Ok(Self::Pointer(R16::try_parse(val)?));
+        }
+
+        if val.starts_with(|c: char| c.is_ascii_digit()) {
+            let address = val
+                .parse::<u16>()
+                .map_err(|_| format!("Expected 0-65535 address, got {val}"))?;
+            return Ok(Self::Immediate(address));
+        }
+
+        Ok(Self::Variable(val.into()))
+    }
+}
+
+#[derive(Debug)]
+pub struct LoadStore {
+    pub register: RegType,
+    pub address: Address,
+}
+
+impl TryFrom<&str> for LoadStore {
+    type Error = String;
+
+    fn try_from(line: &str) -> Result<Self, Self::Error> {
+        let items: Vec<&str> = line.split_ascii_whitespace().collect();
+        // This includes the operator
+        if items.len() < 3 {
+            return Err(format!("Exected 2 arguments got {}", items.len() - 1));
+        }
+
+        let register = if items[1].starts_with('l') {
+            R16::try_parse(items[1])?.parse()
+        } else {
+            R8::try_parse(items[1])?.parse()
+        };
+        let address = items[2].try_into()?;
+        Ok(Self { register, address })
+    }
+}
+
 #[derive(Debug)]
 #[allow(dead_code)]
 pub struct InstrLine<T> {
@@ -179,15 +309,73 @@ impl<T> InstrLine<T> {
     pub fn inner(&self) -> &T {
         &self.instr
     }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
 }
 
 #[derive(Debug)]
+#[allow(dead_code)]
 pub enum Instruction {
     Add(InstrLine<Arg2<R8, R8>>),
     AddI(InstrLine<Arg2<R8, I8>>),
+    Cmp(InstrLine<Arg2<R8, R8>>),
+    CmpI(InstrLine<Arg2<R8, I8>>),
+    Eq(InstrLine<Arg2<R8, R8>>),
+    EqI(InstrLine<Arg2<R8, I8>>),
     Syscall(InstrLine<Arg0>),
     Sv(InstrLine<String>),
     Uv(InstrLine<Arg0>),
+    Label(InstrLine<String>),
+    Jmp(InstrLine<String>),
+    Beq(InstrLine<String>),
+    Bne(InstrLine<String>),
+    Bgt(InstrLine<String>),
+    Blt(InstrLine<String>),
+    Call(InstrLine<String>),
+    Ret(InstrLine<Arg0>),
+    Push(InstrLine<Arg1<R8>>),
+    PushL(InstrLine<Arg1<R16>>),
+    Pop(InstrLine<Arg1<R8>>),
+    PopL(InstrLine<Arg1<R16>>),
+    Ldb(InstrLine<LoadStore>),
+    Ldw(InstrLine<LoadStore>),
+    Stb(InstrLine<LoadStore>),
+    Stw(InstrLine<LoadStore>),
+}
+
+impl Instruction {
+    /// Source line of the instruction, starting at 1
+    pub fn line(&self) -> usize {
+        match self {
+            Instruction::Add(instr) => instr.line(),
+            Instruction::AddI(instr) => instr.line(),
+            Instruction::Cmp(instr) => instr.line(),
+            Instruction::CmpI(instr) => instr.line(),
+            Instruction::Eq(instr) => instr.line(),
+            Instruction::EqI(instr) => instr.line(),
+            Instruction::Syscall(instr) => instr.line(),
+            Instruction::Sv(instr) => instr.line(),
+            Instruction::Uv(instr) => instr.line(),
+            Instruction::Label(instr) => instr.line(),
+            Instruction::Jmp(instr) => instr.line(),
+            Instruction::Beq(instr) => instr.line(),
+            Instruction::Bne(instr) => instr.line(),
+            Instruction::Bgt(instr) => instr.line(),
+            Instruction::Blt(instr) => instr.line(),
+            Instruction::Call(instr) => instr.line(),
+            Instruction::Ret(instr) => instr.line(),
+            Instruction::Push(instr) => instr.line(),
+            Instruction::PushL(instr) => instr.line(),
+            Instruction::Pop(instr) => instr.line(),
+            Instruction::PopL(instr) => instr.line(),
+            Instruction::Ldb(instr) => instr.line(),
+            Instruction::Ldw(instr) => instr.line(),
+            Instruction::Stb(instr) => instr.line(),
+            Instruction::Stw(instr) => instr.line(),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -290,7 +478,29 @@ fn parse_variables(src: &str) -> Vec<Variable> {
     variables
 }
 
+fn parse_label_arg(idx: usize, instr: &str, line: &str) -> Result<InstrLine<String>, String> {
+    let args: Vec<&str> = line.split_ascii_whitespace().collect();
+    if args.len() < 2 {
+        return Err(format!("On line: {idx}: '{instr}' requires a label"));
+    }
+    Ok(InstrLine::new(args[1].into(), idx))
+}
+
+/// 16-bit registers are prefixed with an `l`
+fn is_16b_arg(line: &str) -> bool {
+    line.split_ascii_whitespace()
+        .nth(1)
+        .is_some_and(|arg| arg.starts_with('l'))
+}
+
 fn parse_instruction_line(idx: usize, line: &str) -> Result<Instruction, String> {
+    if let Some(label) = line.strip_suffix(':') {
+        if label.is_empty() || label.contains(char::is_whitespace) {
+            return Err(format!("On line: {idx}: Invalid label '{label}'"));
+        }
+        return Ok(Instruction::Label(InstrLine::new(label.into(), idx)));
+    }
+
     let instr = line.split_ascii_whitespace().next().unwrap().to_lowercase();
 
     if instr == "add" {
@@ -303,6 +513,26 @@ fn parse_instruction_line(idx: usize, line: &str) -> Result<Instruction, String>
             Arg2::<R8, I8>::try_parse(line)?,
             idx,
         )))
+    } else if instr == "cmp" {
+        Ok(Instruction::Cmp(InstrLine::new(
+            Arg2::<R8, R8>::try_parse(line)?,
+            idx,
+        )))
+    } else if instr == "cmpi" {
+        Ok(Instruction::CmpI(InstrLine::new(
+            Arg2::<R8, I8>::try_parse(line)?,
+            idx,
+        )))
+    } else if instr == "eq" {
+        Ok(Instruction::Eq(InstrLine::new(
+            Arg2::<R8, R8>::try_parse(line)?,
+            idx,
+        )))
+    } else if instr == "eqi" {
+        Ok(Instruction::EqI(InstrLine::new(
+            Arg2::<R8, I8>::try_parse(line)?,
+            idx,
+        )))
     } else if instr == "syscall" {
         Ok(Instruction::Syscall(InstrLine::new(Arg0 {}, idx)))
     } else if instr == "uv" {
@@ -313,6 +543,48 @@ fn parse_instruction_line(idx: usize, line: &str) -> Result<Instruction, String>
             panic!("SV requires an argument");
         }
         Ok(Instruction::Sv(InstrLine::new(args[1].into(), idx)))
+    } else if instr == "jmp" {
+        Ok(Instruction::Jmp(parse_label_arg(idx, &instr, line)?))
+    } else if instr == "beq" {
+        Ok(Instruction::Beq(parse_label_arg(idx, &instr, line)?))
+    } else if instr == "bne" {
+        Ok(Instruction::Bne(parse_label_arg(idx, &instr, line)?))
+    } else if instr == "bgt" {
+        Ok(Instruction::Bgt(parse_label_arg(idx, &instr, line)?))
+    } else if instr == "blt" {
+        Ok(Instruction::Blt(parse_label_arg(idx, &instr, line)?))
+    } else if instr == "call" {
+        Ok(Instruction::Call(parse_label_arg(idx, &instr, line)?))
+    } else if instr == "ret" {
+        Ok(Instruction::Ret(InstrLine::new(Arg0 {}, idx)))
+    } else if instr == "push" && is_16b_arg(line) {
+        Ok(Instruction::PushL(InstrLine::new(
+            Arg1::<R16>::try_parse(line)?,
+            idx,
+        )))
+    } else if instr == "push" {
+        Ok(Instruction::Push(InstrLine::new(
+            Arg1::<R8>::try_parse(line)?,
+            idx,
+        )))
+    } else if instr == "pop" && is_16b_arg(line) {
+        Ok(Instruction::PopL(InstrLine::new(
+            Arg1::<R16>::try_parse(line)?,
+            idx,
+        )))
+    } else if instr == "pop" {
+        Ok(Instruction::Pop(InstrLine::new(
+            Arg1::<R8>::try_parse(line)?,
+            idx,
+        )))
+    } else if instr == "ldb" {
+        Ok(Instruction::Ldb(InstrLine::new(line.try_into()?, idx)))
+    } else if instr == "ldw" {
+        Ok(Instruction::Ldw(InstrLine::new(line.try_into()?, idx)))
+    } else if instr == "stb" {
+        Ok(Instruction::Stb(InstrLine::new(line.try_into()?, idx)))
+    } else if instr == "stw" {
+        Ok(Instruction::Stw(InstrLine::new(line.try_into()?, idx)))
     } else {
         Err(format!(
             "On line: {idx}: Instruction '{instr}' has not been implemented"
@@ -328,10 +600,12 @@ pub fn parse_source(source: &str) -> Result<ASTTree, String> {
         0
     };
 
+    // Line numbers start at 1 and count the variable section
+    let first_line = source[..var_end].matches('\n').count() + 1;
     let instructions: Vec<Instruction> = source[var_end..]
         .lines()
         .enumerate()
-        .map(|(idx, line)| (idx, line.trim()))
+        .map(|(idx, line)| (first_line + idx, line.trim()))
         .filter(|(_, line)| !line.is_empty())
         .filter(|(_, line)| !line.starts_with('#'))
         .map(|(idx, line)| parse_instruction_line(idx, line).unwrap())
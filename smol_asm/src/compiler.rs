@@ -1,103 +1,122 @@
-use smol_file::{SmolFile, Storage, StorageItem};
+use std::collections::HashMap;
 
-use crate::ast::{ASTTree, Arg2, Instruction, R8Regs, Variable, I8, R8};
+use smol_file::{DebugInfo, LineEntry, SmolFile, Storage, StorageItem, VariableInfo};
+use smol_isa::{OperandKind, Register};
 
-trait Compile {
-    fn compile(&self) -> Vec<u8>;
+use crate::ast::{
+    ASTTree, Address, Arg1, Arg2, InstrLine, Instruction, LoadStore, R16Regs, R8Regs, RegType,
+    Variable, I8, R16, R8,
+};
+
+/// Operands with their kind in the ISA and their encoded value
+trait Operands {
+    fn operands(&self) -> Vec<(OperandKind, u16)>;
 }
 
-impl Compile for Arg2<R8, R8> {
-    fn compile(&self) -> Vec<u8> {
-        let arg = (self.arg2.compile()[0] << 4) | self.arg1.compile()[0];
-        vec![arg]
+impl Operands for Arg1<R8> {
+    fn operands(&self) -> Vec<(OperandKind, u16)> {
+        vec![(OperandKind::Register, self.arg1.encoding())]
     }
 }
 
-impl Compile for Arg2<R8, I8> {
-    fn compile(&self) -> Vec<u8> {
-        let arg = self.arg1.compile()[0];
-        vec![arg, self.arg2.compile()[0]]
+impl Operands for Arg1<R16> {
+    fn operands(&self) -> Vec<(OperandKind, u16)> {
+        vec![(OperandKind::Register, self.arg1.encoding())]
     }
 }
 
-impl Compile for R8 {
-    fn compile(&self) -> Vec<u8> {
-        let val: u8 = match self.register {
-            R8Regs::R0 => 0b0000,
-            R8Regs::R1 => 0b0001,
-            R8Regs::R2 => 0b0010,
-            R8Regs::R3 => 0b0011,
-            R8Regs::R4 => 0b0100,
-            R8Regs::R5 => 0b0101,
-            R8Regs::R6 => 0b0110,
-            R8Regs::R7 => 0b0111,
-        };
-        vec![val]
+impl Operands for Arg2<R8, R8> {
+    fn operands(&self) -> Vec<(OperandKind, u16)> {
+        vec![
+            (OperandKind::Register, self.arg1.encoding()),
+            (OperandKind::SourceRegister, self.arg2.encoding()),
+        ]
     }
 }
 
-impl Compile for I8 {
-    fn compile(&self) -> Vec<u8> {
-        vec![self.value]
+impl Operands for Arg2<R8, I8> {
+    fn operands(&self) -> Vec<(OperandKind, u16)> {
+        vec![
+            (OperandKind::Register, self.arg1.encoding()),
+            (OperandKind::Immediate8, self.arg2.value as u16),
+        ]
     }
 }
 
-#[allow(dead_code)]
-enum ALUType {
-    Add,
-    Subtract,
-    And,
-    Or,
-    Xor,
-    Not,
-    Equality,
-    IncrDecr,
+impl R8 {
+    fn compile_regs(register: &R8Regs) -> u8 {
+        let register = match register {
+            R8Regs::R0 => Register::R0,
+            R8Regs::R1 => Register::R1,
+            R8Regs::R2 => Register::R2,
+            R8Regs::R3 => Register::R3,
+            R8Regs::R4 => Register::R4,
+            R8Regs::R5 => Register::R5,
+            R8Regs::R6 => Register::R6,
+            R8Regs::R7 => Register::R7,
+        };
+        register.encoding()
+    }
+
+    fn encoding(&self) -> u16 {
+        R8::compile_regs(&self.register) as u16
+    }
 }
 
-/// If `opcode[2:4] != 0b111`:
-/// `opcode[5]` - Source
-///  * `0b0` - Register
-///  * `0b1` - Immediate
-///
-/// Otherwise:
-/// `opcode[5]` - Function
-///  * `0b0` - Increment
-///  * `0b1` - Decrement
-#[allow(dead_code)]
-enum ALUSrc {
-    Register,
-    Immidiate,
-    Incerement,
-    Decrement,
+impl R16 {
+    fn compile_regs(register: &R16Regs) -> u8 {
+        let register = match register {
+            R16Regs::L0 => Register::L0,
+            R16Regs::L1 => Register::L1,
+        };
+        register.encoding()
+    }
+
+    fn encoding(&self) -> u16 {
+        R16::compile_regs(&self.register) as u16
+    }
 }
 
-fn compile_alu_equality(tt: ALUType, source: ALUSrc, is_16b: bool, noop: bool) -> u8 {
-    #[allow(clippy::unusual_byte_groupings)]
-    let op = match tt {
-        ALUType::Add => 0b00_000_0_0_0,
-        ALUType::Subtract => 0b00_001_0_0_0,
-        ALUType::And => 0b00_010_0_0_0,
-        ALUType::Or => 0b00_011_0_0_0,
-        ALUType::Xor => 0b00_100_0_0_0,
-        ALUType::Not => 0b00_101_0_0_0,
-        ALUType::Equality => 0b00_110_0_0_0,
-        ALUType::IncrDecr => 0b00_111_0_0_0,
-    };
+/// Encode an instruction with the form of the ISA matching the mnemonic, width and operands
+fn encode(mnemonic: &str, wide: bool, operands: &[(OperandKind, u16)]) -> Vec<u8> {
+    let kinds: Vec<OperandKind> = operands.iter().map(|(kind, _)| *kind).collect();
+    let values: Vec<u16> = operands.iter().map(|(_, value)| *value).collect();
+    smol_isa::find(mnemonic, wide, &kinds)
+        .unwrap_or_else(|| panic!("'{mnemonic}' with {kinds:?} is not part of the ISA"))
+        .encode(&values)
+}
 
-    let mut op = match source {
-        ALUSrc::Immidiate | ALUSrc::Decrement => op | 0b100,
-        _ => op,
+/// `mnemonic` is `ldb`, `ldw`, `stb` or `stw`, `wide` for the word forms
+fn compile_load_store(
+    mnemonic: &str,
+    wide: bool,
+    instr: &LoadStore,
+    ast: &ASTTree,
+    storage: &Storage,
+) -> Vec<u8> {
+    let register = match &instr.register {
+        RegType::R8(register) => R8::compile_regs(register),
+        RegType::R16(register) => R16::compile_regs(register),
+        RegType::I8(_) => unreachable!("Load and store only use registers"),
     };
 
-    if is_16b {
-        op |= 0b10;
-    }
-
-    if noop {
-        op |= 0b1;
-    }
+    let address = match &instr.address {
+        Address::StackOffset(offset) => (OperandKind::StackOffset, *offset as u16),
+        Address::Immediate(address) => (OperandKind::Address, *address),
+        Address::Variable(name) => {
+            // Variables live in the second half of the memory
+            let address = variable_offset(name, ast, storage) + (u16::MAX / 2);
+            (OperandKind::Address, address)
+        }
+        Address::StackRegister(address) => (OperandKind::StackRegister, address.encoding()),
+        Address::Pointer(address) => (OperandKind::Pointer, address.encoding()),
+    };
 
-    op
+    encode(
+        mnemonic,
+        wide,
+        &[(OperandKind::Register, register as u16), address],
+    )
 }
 
 fn compile_variables(vars: &Vec<Variable>) -> Storage {
@@ -151,45 +170,153 @@ fn variable_offset(name: &str, ast: &ASTTree, storage: &Storage) -> u16 {
     storage.items[idx].offset
 }
 
-pub fn compile_ast(ast: ASTTree) -> SmolFile {
+/// Branch offset that is filled in once all labels are known
+struct Fixup<'a> {
+    /// Start of the branch instruction, offsets are relative to this
+    origin: usize,
+    mnemonic: &'static str,
+    label: &'a InstrLine<String>,
+}
+
+/// Labels always use a 16 bit offset so the instruction size is known upfront
+fn compile_label_branch<'a>(
+    mnemonic: &'static str,
+    label: &'a InstrLine<String>,
+    instructions: &[u8],
+    fixups: &mut Vec<Fixup<'a>>,
+) -> Vec<u8> {
+    fixups.push(Fixup {
+        origin: instructions.len(),
+        mnemonic,
+        label,
+    });
+    encode(mnemonic, true, &[(OperandKind::Offset16, 0)])
+}
+
+/// Compile the tree into an object file and its debug information.
+/// The source path of the debug information is left for the caller to fill in.
+pub fn compile_ast(ast: ASTTree) -> (SmolFile, DebugInfo) {
     let storage = compile_variables(&ast.variables);
 
-    let instructions: Vec<u8> = ast
-        .instructions
-        .iter()
-        .flat_map(|instr| match instr {
-            Instruction::Add(instr) => {
-                let mut args = instr.inner().compile();
-                let op = compile_alu_equality(ALUType::Add, ALUSrc::Register, false, false);
-                args.insert(0, op);
-                args
+    let mut instructions: Vec<u8> = Vec::new();
+    let mut lines: Vec<LineEntry> = Vec::new();
+    let mut labels: HashMap<&str, usize> = HashMap::new();
+    let mut fixups: Vec<Fixup> = Vec::new();
+
+    for instr in &ast.instructions {
+        let bytes = match instr {
+            Instruction::Add(instr) => encode("add", false, &instr.inner().operands()),
+            Instruction::AddI(instr) => encode("addi", false, &instr.inner().operands()),
+            Instruction::Cmp(instr) => encode("cmp", false, &instr.inner().operands()),
+            Instruction::CmpI(instr) => encode("cmpi", false, &instr.inner().operands()),
+            Instruction::Eq(instr) => encode("eq", false, &instr.inner().operands()),
+            Instruction::EqI(instr) => encode("eqi", false, &instr.inner().operands()),
+            Instruction::Sv(name) => {
+                let offset = variable_offset(name.inner(), &ast, &storage);
+                encode("sv", true, &[(OperandKind::Immediate16, offset)])
             }
-            Instruction::AddI(instr) => {
-                let mut args = instr.inner().compile();
-                let op = compile_alu_equality(ALUType::Add, ALUSrc::Immidiate, false, false);
-                args.insert(0, op);
-                args
+            Instruction::Uv(_) => encode("uv", false, &[]),
+            Instruction::Syscall(_) => encode("syscall", false, &[]),
+            Instruction::Label(label) => {
+                if labels.insert(label.inner(), instructions.len()).is_some() {
+                    panic!(
+                        "On line: {}: Label '{}' is defined twice",
+                        label.line(),
+                        label.inner()
+                    );
+                }
+                Vec::new()
             }
-            Instruction::Sv(name) => {
-                let name = name.inner();
-                let offset = variable_offset(name, &ast, &storage);
-                let [li, mi] = offset.to_le_bytes();
-                // Stack load variable immediate 16 bit
-                [0b10101100, li, mi].into()
+            Instruction::Jmp(label) => {
+                compile_label_branch("jmp", label, &instructions, &mut fixups)
+            }
+            Instruction::Beq(label) => {
+                compile_label_branch("beq", label, &instructions, &mut fixups)
+            }
+            Instruction::Bne(label) => {
+                compile_label_branch("bne", label, &instructions, &mut fixups)
+            }
+            Instruction::Bgt(label) => {
+                compile_label_branch("bgt", label, &instructions, &mut fixups)
             }
-            Instruction::Uv(_) => {
-                // hardcoded UV
-                [0b10110000].to_vec()
+            Instruction::Blt(label) => {
+                compile_label_branch("blt", label, &instructions, &mut fixups)
             }
-            Instruction::Syscall(_) => {
-                // hardcoded syscall binary
-                [0b11101111].to_vec()
+            Instruction::Call(label) => {
+                compile_label_branch("call", label, &instructions, &mut fixups)
             }
+            Instruction::Ret(_) => encode("ret", false, &[]),
+            Instruction::Push(instr) => encode("push", false, &instr.inner().operands()),
+            Instruction::PushL(instr) => encode("push", true, &instr.inner().operands()),
+            Instruction::Pop(instr) => encode("pop", false, &instr.inner().operands()),
+            Instruction::PopL(instr) => encode("pop", true, &instr.inner().operands()),
+            Instruction::Ldb(instr) => {
+                compile_load_store("ldb", false, instr.inner(), &ast, &storage)
+            }
+            Instruction::Ldw(instr) => {
+                compile_load_store("ldw", true, instr.inner(), &ast, &storage)
+            }
+            Instruction::Stb(instr) => {
+                compile_load_store("stb", false, instr.inner(), &ast, &storage)
+            }
+            Instruction::Stw(instr) => {
+                compile_load_store("stw", true, instr.inner(), &ast, &storage)
+            }
+        };
+        if !bytes.is_empty() {
+            lines.push(LineEntry {
+                line: instr.line() as u32,
+                ic: instructions.len() as u16,
+            });
+        }
+        instructions.extend(bytes);
+    }
+
+    for fixup in fixups {
+        let label = fixup.label;
+        let target = *labels.get(label.inner().as_str()).unwrap_or_else(|| {
+            panic!(
+                "On line: {}: Label '{}' is not defined",
+                label.line(),
+                label.inner()
+            )
+        });
+        let offset = i16::try_from(target as isize - fixup.origin as isize).unwrap_or_else(|_| {
+            panic!(
+                "On line: {}: Label '{}' is too far away",
+                label.line(),
+                label.inner()
+            )
+        });
+        let bytes = encode(
+            fixup.mnemonic,
+            true,
+            &[(OperandKind::Offset16, offset as u16)],
+        );
+        instructions.splice(fixup.origin..fixup.origin + bytes.len(), bytes);
+    }
+
+    let variables = ast
+        .variables
+        .iter()
+        .zip(&storage.items)
+        .map(|(var, item)| VariableInfo {
+            name: var.name.clone(),
+            address: item.offset + u16::MAX / 2,
+            size: var.size,
         })
         .collect();
+    let debug_info = DebugInfo {
+        source: String::new(),
+        lines,
+        variables,
+    };
 
-    SmolFile {
-        storage,
-        instructions,
-    }
+    (
+        SmolFile {
+            storage,
+            instructions,
+        },
+        debug_info,
+    )
 }
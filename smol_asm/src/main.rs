@@ -1,4 +1,4 @@
-use std::{ fs, process::exit};
+use std::{fs, process::exit};
 
 mod ast;
 mod compiler;
@@ -12,6 +12,12 @@ fn main() {
 
     let file_contents = fs::read_to_string(&args[1]).unwrap();
     let tree = ast::parse_source(&file_contents).unwrap();
-    let binary = compiler::compile_ast(tree);
-    binary.save(&format!("{}.obj", &args[1]));
+    let (binary, mut debug_info) = compiler::compile_ast(tree);
+    let object_path = format!("{}.obj", &args[1]);
+    debug_info.source = fs::canonicalize(&args[1])
+        .unwrap()
+        .to_string_lossy()
+        .into_owned();
+    debug_info.save(&smol_file::DebugInfo::path_for(&object_path));
+    binary.save(&object_path);
 }
@@ -0,0 +1,21 @@
+use std::{fs, process::Command};
+
+use smol_file::SmolFile;
+
+mod variable_test;
+
+/// Assemble `source` with the smol_asm binary and load the object file
+pub fn assemble(name: &str, source: &str) -> SmolFile {
+    let dir = std::env::temp_dir().join(format!("smol_asm_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{name}.smol"));
+    fs::write(&path, source).unwrap();
+
+    let status = Command::new(env!("CARGO_BIN_EXE_smol_asm"))
+        .arg(&path)
+        .status()
+        .unwrap();
+    assert!(status.success(), "smol_asm failed on {name}");
+
+    SmolFile::load(&format!("{}.obj", path.display()))
+}
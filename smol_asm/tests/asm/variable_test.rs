@@ -0,0 +1,47 @@
+use smol_vm::{Register, Vm};
+
+use super::assemble;
+
+/// Variables start in the second half of the memory
+const VARIABLES: usize = (u16::MAX / 2) as usize;
+
+#[test]
+pub fn it_stores_and_loads_a_variable_named_like_a_pointer() {
+    let file = assemble(
+        "pointer_like",
+        "---
+len 1
+---
+addi r0 5
+stb r0 len
+ldb r1 len
+",
+    );
+    let mut vm = Vm::default();
+    vm.load_program(file).unwrap();
+    vm.run().unwrap();
+
+    assert_eq!(vm.stack.memory()[VARIABLES], 5);
+    assert_eq!(vm.registers[Register::R1], 5);
+}
+
+#[test]
+pub fn it_stores_and_loads_a_variable_named_like_the_stack_pointer() {
+    let file = assemble(
+        "stack_pointer_like",
+        "---
+len 1
+speed 1
+---
+addi r0 7
+stb r0 speed
+ldb r1 speed
+",
+    );
+    let mut vm = Vm::default();
+    vm.load_program(file).unwrap();
+    vm.run().unwrap();
+
+    assert_eq!(vm.stack.memory()[VARIABLES + 1], 7);
+    assert_eq!(vm.registers[Register::R1], 7);
+}
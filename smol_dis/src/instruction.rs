@@ -0,0 +1,113 @@
+use std::fmt;
+
+use smol_isa::Operand;
+
+/// Reason the bytes at an instruction counter don't decode into an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisasmError {
+    /// The opcode does not decode into an instruction
+    InvalidOpcode,
+    /// `ic` points past the end of the instructions,
+    /// or the instruction ends past the 64kib `ic` can address
+    InstructionOutOfRange,
+    /// The operands of the instruction are cut off by the end of the instructions
+    TruncatedInstruction,
+    /// The register encoding is not mapped to a register
+    InvalidRegister(u8),
+}
+
+impl From<smol_isa::DecodeError> for DisasmError {
+    fn from(err: smol_isa::DecodeError) -> Self {
+        match err {
+            smol_isa::DecodeError::InvalidOpcode => DisasmError::InvalidOpcode,
+            smol_isa::DecodeError::Truncated => DisasmError::TruncatedInstruction,
+            smol_isa::DecodeError::InvalidRegister(reg) => DisasmError::InvalidRegister(reg),
+        }
+    }
+}
+
+impl fmt::Display for DisasmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidOpcode => write!(f, "invalid opcode"),
+            Self::InstructionOutOfRange => write!(f, "instruction counter out of range"),
+            Self::TruncatedInstruction => write!(f, "truncated instruction"),
+            Self::InvalidRegister(reg) => write!(f, "invalid register {reg:#06b}"),
+        }
+    }
+}
+
+/// Instruction decoded without executing it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    /// Instruction counter of the first byte
+    pub ic: u16,
+    /// Size in bytes including the operands
+    pub len: u16,
+    pub opcode: u8,
+    /// Mnemonic of the instruction as written in the assembly
+    pub mnemonic: &'static str,
+    pub operands: Vec<Operand>,
+}
+
+impl Instruction {
+    /// Instruction counter a branch with an offset jumps to
+    pub fn branch_target(&self) -> Option<i32> {
+        self.operands.iter().find_map(|operand| match operand {
+            Operand::Offset(offset) => Some(self.ic as i32 + *offset as i32),
+            _ => None,
+        })
+    }
+
+    /// Instruction counter of the following instruction
+    pub fn end(&self) -> u16 {
+        self.ic + self.len
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.mnemonic)?;
+        for operand in &self.operands {
+            write!(f, " {operand}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Decode the instruction at `ic`, the same way the VM does before executing it
+pub fn decode(instructions: &[u8], ic: u16) -> Result<Instruction, DisasmError> {
+    let bytes = instructions
+        .get(ic as usize..)
+        .filter(|bytes| !bytes.is_empty())
+        .ok_or(DisasmError::InstructionOutOfRange)?;
+    let decoded = smol_isa::decode(bytes)?;
+    // The instruction following this one has to be addressable by `ic`
+    let len = decoded.def.size();
+    ic.checked_add(len)
+        .ok_or(DisasmError::InstructionOutOfRange)?;
+
+    Ok(Instruction {
+        ic,
+        len,
+        opcode: decoded.opcode,
+        mnemonic: decoded.def.mnemonic,
+        operands: decoded.operands.into_iter().flatten().collect(),
+    })
+}
+
+/// Decode every instruction from the start, stops at the first one that doesn't decode
+pub fn disassemble(instructions: &[u8]) -> (Vec<Instruction>, Option<(u16, DisasmError)>) {
+    let mut decoded = Vec::new();
+    let mut ic = 0;
+    while (ic as usize) < instructions.len() {
+        match decode(instructions, ic) {
+            Ok(instruction) => {
+                ic = instruction.end();
+                decoded.push(instruction);
+            }
+            Err(err) => return (decoded, Some((ic, err))),
+        }
+    }
+    (decoded, None)
+}
@@ -0,0 +1,90 @@
+//! Disassembler for smol object files.
+//!
+//! Instructions and their operands are decoded with [smol_isa] like in the VM,
+//! so both always agree on the length and meaning of an instruction.
+
+use std::fmt;
+
+use smol_file::{DebugInfo, SmolFile};
+
+mod instruction;
+
+pub use instruction::{decode, disassemble, DisasmError, Instruction};
+pub use smol_isa::Operand;
+
+/// One line of the listing, an instruction or a byte that doesn't decode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub ic: u16,
+    /// Raw bytes of the instruction including the operands
+    pub bytes: Vec<u8>,
+    /// The instruction, `Err` if the byte at `ic` doesn't start a valid instruction
+    pub instruction: Result<Instruction, DisasmError>,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes: Vec<String> = self
+            .bytes
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        write!(f, "{:5}: {:<12} ", self.ic, bytes.join(" "))?;
+        match &self.instruction {
+            Ok(instruction) => {
+                write!(f, "{instruction}")?;
+                if let Some(target) = instruction.branch_target() {
+                    write!(f, " ; -> {target}")?;
+                }
+                Ok(())
+            }
+            Err(err) => write!(f, ".byte {:#04x} ; {err}", self.bytes[0]),
+        }
+    }
+}
+
+/// Decode all the instructions, a byte that doesn't decode gets its own line
+/// and decoding starts again at the next byte.
+/// Bytes past the 64kib `ic` can address aren't listed.
+pub fn lines(instructions: &[u8]) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut ic = 0_u16;
+    while (ic as usize) < instructions.len() {
+        let instruction = decode(instructions, ic);
+        let len = instruction
+            .as_ref()
+            .map_or(1, |instruction| instruction.len);
+        lines.push(Line {
+            ic,
+            bytes: instructions[ic as usize..ic as usize + len as usize].to_vec(),
+            instruction,
+        });
+        match ic.checked_add(len) {
+            Some(next) => ic = next,
+            None => break,
+        }
+    }
+    lines
+}
+
+/// Listing of the variables and the instructions of the file.
+/// Variables get their name from the debug information when it's available.
+pub fn listing(file: &SmolFile, debug_info: Option<&DebugInfo>) -> String {
+    let mut text = String::from("---\n");
+    for (idx, item) in file.storage.items.iter().enumerate() {
+        let name = debug_info
+            .and_then(|info| info.variables.get(idx))
+            .map_or_else(|| format!("var{idx}"), |var| var.name.clone());
+        text.push_str(&format!("{name} {}", item.size));
+        if let Some(data) = &item.init_data {
+            let data: Vec<String> = data.iter().map(|byte| format!("{byte:02x}")).collect();
+            text.push_str(&format!(" ; {}", data.join(" ")));
+        }
+        text.push('\n');
+    }
+    text.push_str("---\n");
+    for line in lines(&file.instructions) {
+        text.push_str(&format!("{line}\n"));
+    }
+    text
+}
@@ -0,0 +1,16 @@
+use std::process::exit;
+
+use smol_file::{DebugInfo, SmolFile};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 2 {
+        println!("Give file as an argument");
+        exit(1);
+    }
+
+    let file = SmolFile::load(&args[1]);
+    // Names of the variables, if the assembler left debug information
+    let debug_info = DebugInfo::load(&DebugInfo::path_for(&args[1])).ok();
+    print!("{}", smol_dis::listing(&file, debug_info.as_ref()));
+}
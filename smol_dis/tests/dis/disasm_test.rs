@@ -0,0 +1,111 @@
+use smol_dis::{decode, disassemble, DisasmError};
+
+#[test]
+pub fn it_decodes_every_family() {
+    let instructions = vec![
+        // ALU Add Immediate
+        0b00_000_1_0_0,
+        0b0000_0010,
+        7,
+        // ALU Compare from Register
+        0b00_110_0_0_1,
+        0b0001_0000,
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        0b0000_1001,
+        // Store Word imm16
+        0b01_1_1_01_00,
+        0b0000_1001,
+        0x00,
+        0x80,
+        // Load Byte sp+reg
+        0b01_0_0_10_00,
+        0b0011_0000,
+        // Stack Push 16-bit immediate
+        0b10_00_11_00,
+        0x34,
+        0x12,
+        // Stack Pop 16-bit Register
+        0b10_01_01_00,
+        0b0000_1010,
+        // Unsave variable
+        0b10_11_00_00,
+        // Branch Equal 16-bit offset
+        0b11_001_100,
+        0xfe,
+        0xff,
+        // Call 8-bit offset
+        0b11_101_000,
+        2,
+        // Return
+        0b11_110_000,
+        // Systemcall
+        0b11_101_111,
+        // Return from interrupt, it only faults once the VM executes it
+        0b11_111_000,
+    ];
+    let (decoded, error) = disassemble(&instructions);
+    let text: Vec<(u16, String)> = decoded
+        .iter()
+        .map(|instruction| (instruction.ic, instruction.to_string()))
+        .collect();
+
+    assert_eq!(error, None);
+    assert_eq!(
+        text,
+        [
+            (0, "addi r2 7".to_string()),
+            (3, "cmp r0 r1".to_string()),
+            (5, "inc l0".to_string()),
+            (7, "stw l0 32768".to_string()),
+            (11, "ldb r0 sp+r3".to_string()),
+            (13, "push 4660".to_string()),
+            (16, "pop l1".to_string()),
+            (18, "uv".to_string()),
+            (19, "beq -2".to_string()),
+            (22, "call +2".to_string()),
+            (24, "ret".to_string()),
+            (25, "syscall".to_string()),
+            (26, "reti".to_string()),
+        ]
+    );
+    assert_eq!(decoded[8].branch_target(), Some(17));
+}
+
+#[test]
+pub fn it_reports_undecodable_instructions() {
+    // ALU Add from Register with the unmapped register 0b1000
+    assert_eq!(
+        decode(&[0b00_000_0_0_0, 0b0000_1000], 0),
+        Err(DisasmError::InvalidRegister(0b1000))
+    );
+    // Push 16-bit immediate without its second byte
+    assert_eq!(
+        decode(&[0b10_00_11_00, 0x34], 0),
+        Err(DisasmError::TruncatedInstruction)
+    );
+    // Pop into an immediate
+    assert_eq!(decode(&[0b10_01_10_00], 0), Err(DisasmError::InvalidOpcode));
+    assert_eq!(decode(&[], 0), Err(DisasmError::InstructionOutOfRange));
+
+    let (decoded, error) = disassemble(&[0b10_11_00_00, 0b10_01_10_00]);
+    assert_eq!(decoded.len(), 1);
+    assert_eq!(error, Some((1, DisasmError::InvalidOpcode)));
+}
+
+#[test]
+pub fn it_stops_at_the_64kib_ic_can_address() {
+    // Unsave variable, one byte past what `ic` can address
+    let instructions = [0b10_11_00_00].repeat(0x10001);
+
+    let (decoded, error) = disassemble(&instructions);
+    assert_eq!(decoded.len(), 0xffff);
+    assert_eq!(error, Some((0xffff, DisasmError::InstructionOutOfRange)));
+
+    let lines = smol_dis::lines(&instructions);
+    assert_eq!(lines.len(), 0x10000);
+    assert_eq!(
+        lines[0xffff].instruction,
+        Err(DisasmError::InstructionOutOfRange)
+    );
+}
@@ -0,0 +1,99 @@
+use smol_dis::{lines, listing, DisasmError};
+use smol_file::{DebugInfo, SmolFile, Storage, StorageItem, VariableInfo};
+
+#[test]
+pub fn it_lists_offsets_bytes_and_mnemonics() {
+    let instructions = vec![
+        // ALU Add Immediate
+        0b00_000_1_0_0,
+        0b0000_0010,
+        7,
+        // Load Byte imm16
+        0b01_0_0_01_00,
+        0b0000_0001,
+        0x00,
+        0x80,
+        // Stack Push 8-bit immediate
+        0b10_00_10_00,
+        5,
+        // Branch Not Equal 8-bit offset
+        0b11_010_000,
+        0xf7,
+    ];
+    let text: Vec<String> = lines(&instructions)
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+
+    assert_eq!(
+        text,
+        [
+            "    0: 04 02 07     addi r2 7",
+            "    3: 44 01 00 80  ldb r1 32768",
+            "    7: 88 05        push 5",
+            "    9: d0 f7        bne -9 ; -> 0",
+        ]
+    );
+}
+
+#[test]
+pub fn it_resumes_after_invalid_bytes() {
+    let instructions = vec![
+        // Stack Pop 8-bit immediate
+        0b10_01_10_00,
+        // Return
+        0b11_110_000,
+        // ALU Add from Register, missing the register byte
+        0b00_000_0_0_0,
+    ];
+    let lines = lines(&instructions);
+
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0].instruction, Err(DisasmError::InvalidOpcode));
+    assert_eq!(
+        lines[0].to_string(),
+        "    0: 98           .byte 0x98 ; invalid opcode"
+    );
+    assert_eq!(lines[1].to_string(), "    1: f0           ret");
+    assert_eq!(lines[2].bytes, [0]);
+    assert_eq!(lines[2].instruction, Err(DisasmError::TruncatedInstruction));
+}
+
+#[test]
+pub fn it_names_variables_from_debug_info() {
+    let file = SmolFile {
+        storage: Storage {
+            total_size: 11,
+            items: vec![
+                StorageItem {
+                    size: 3,
+                    offset: 0,
+                    init_data: Some(b"hi\n".to_vec()),
+                },
+                StorageItem {
+                    size: 4,
+                    offset: 3,
+                    init_data: None,
+                },
+            ],
+        },
+        instructions: vec![
+            // Systemcall
+            0b11_101_111,
+        ],
+    };
+    let debug_info = DebugInfo {
+        variables: vec![VariableInfo {
+            name: "msg".to_string(),
+            address: u16::MAX / 2,
+            size: 3,
+        }],
+        ..Default::default()
+    };
+
+    assert_eq!(
+        listing(&file, Some(&debug_info)),
+        "---\nmsg 3 ; 68 69 0a\nvar1 4\n---\n    0: ef           syscall\n"
+    );
+    assert!(listing(&file, None).starts_with("---\nvar0 3 ; 68 69 0a\n"));
+}
@@ -0,0 +1,2 @@
+mod disasm_test;
+mod listing_test;
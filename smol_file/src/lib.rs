@@ -1,4 +1,4 @@
-use std::fs;
+use std::{fs, str::FromStr};
 
 #[derive(Debug)]
 pub struct StorageItem {
@@ -22,17 +22,27 @@ pub struct Storage {
 }
 
 impl Storage {
-    fn load(data: &[u8]) -> Self {
-        let total_size = u16::from_le_bytes([data[0], data[1]]);
-        let mut bytes = &data[2..total_size as usize + 2];
+    fn load(data: &[u8]) -> Result<Self, String> {
+        let truncated = || "The storage is cut off".to_string();
+        let total_size = read_u16(data, 0).ok_or_else(truncated)?;
+        let mut bytes = data.get(2..total_size as usize + 2).ok_or_else(truncated)?;
 
         let mut items: Vec<StorageItem> = Vec::new();
         while !bytes.is_empty() {
-            let size = u16::from_le_bytes([bytes[0], bytes[1]]);
+            let (Some(size), Some(offset)) = (read_u16(bytes, 0), read_u16(bytes, 2)) else {
+                return Err(truncated());
+            };
             // We get the real size by removing the init_data flag
             let rsize = size & 0x7fff;
             // Add the variable stack address offset
-            let offset = u16::from_le_bytes([bytes[2], bytes[3]]) + (u16::MAX / 2);
+            let offset = offset
+                .checked_add(u16::MAX / 2)
+                .ok_or_else(|| format!("Variable offset {offset} is out of the memory"))?;
+
+            if 4 + rsize as usize > bytes.len() {
+                break;
+            }
+
             // If the init_data flag is set
             let init_data = if size & 0x8000 == 0x8000 {
                 Some(bytes[4..rsize as usize + 4].into())
@@ -40,10 +50,6 @@ impl Storage {
                 None
             };
 
-            if 4 + rsize as usize > bytes.len() {
-                break;
-            }
-
             // If the init_data flag is set
             bytes = if size & 0x8000 == 0x8000 {
                 &bytes[4 + rsize as usize..]
@@ -58,10 +64,15 @@ impl Storage {
             })
         }
 
-        Self { items, total_size }
+        Ok(Self { items, total_size })
     }
 }
 
+/// Little endian word at `at`, `None` if it's cut off
+fn read_u16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes([*bytes.get(at)?, *bytes.get(at + 1)?]))
+}
+
 #[derive(Debug)]
 pub struct SmolFile {
     pub storage: Storage,
@@ -89,14 +100,130 @@ impl SmolFile {
     }
 
     pub fn load(path: &str) -> Self {
-        let file_bytes = fs::read(path).unwrap();
-        let storage_size = u16::from_le_bytes([file_bytes[0], file_bytes[1]]) as usize;
-        let storage = Storage::load(&file_bytes);
-        let instructions: Vec<u8> = file_bytes[storage_size + 2..].into();
+        Self::try_load(path).unwrap()
+    }
+
+    /// Like [SmolFile::load], but a missing or malformed file is an error instead of a panic
+    pub fn try_load(path: &str) -> Result<Self, String> {
+        let file_bytes = fs::read(path).map_err(|err| format!("{path}: {err}"))?;
+        Self::from_bytes(&file_bytes).map_err(|err| format!("{path}: {err}"))
+    }
+
+    pub fn from_bytes(file_bytes: &[u8]) -> Result<Self, String> {
+        let storage = Storage::load(file_bytes)?;
+        let instructions: Vec<u8> = file_bytes[storage.total_size as usize + 2..].into();
 
-        Self {
+        Ok(Self {
             storage,
             instructions,
+        })
+    }
+}
+
+/// Source line of the instruction starting at `ic`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineEntry {
+    /// Line in the source file, starting at 1
+    pub line: u32,
+    pub ic: u16,
+}
+
+/// Variable of the `---` section
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariableInfo {
+    pub name: String,
+    /// Memory address of the variable, the variable space offset is included
+    pub address: u16,
+    pub size: u16,
+}
+
+/// Debug information written next to the object file by the assembler,
+/// see [DebugInfo::path_for].
+///
+/// It's a text file with one entry per line:
+/// - `source <path>` - absolute path of the assembly source
+/// - `line <line> <ic>` - the instruction at `ic` is on `line`
+/// - `var <name> <address> <size>` - variable in memory
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DebugInfo {
+    pub source: String,
+    /// Line of every instruction, sorted by `ic`
+    pub lines: Vec<LineEntry>,
+    pub variables: Vec<VariableInfo>,
+}
+
+impl DebugInfo {
+    /// Path of the debug information of the object file
+    pub fn path_for(object_path: &str) -> String {
+        format!("{object_path}.dbg")
+    }
+
+    /// Line of the instruction at `ic`
+    pub fn line_of(&self, ic: u16) -> Option<u32> {
+        self.lines
+            .iter()
+            .find(|entry| entry.ic == ic)
+            .map(|entry| entry.line)
+    }
+
+    /// First instruction on or after `line`
+    pub fn ic_of(&self, line: u32) -> Option<&LineEntry> {
+        self.lines
+            .iter()
+            .filter(|entry| entry.line >= line)
+            .min_by_key(|entry| entry.line)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("source {}\n", self.source);
+        for entry in &self.lines {
+            text.push_str(&format!("line {} {}\n", entry.line, entry.ic));
+        }
+        for var in &self.variables {
+            text.push_str(&format!("var {} {} {}\n", var.name, var.address, var.size));
         }
+        text
     }
+
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        let mut info = Self::default();
+        for (idx, line) in text.lines().enumerate() {
+            let invalid = || format!("Invalid debug info on line {}: '{line}'", idx + 1);
+            let mut items = line.split(' ');
+            match items.next() {
+                Some("source") => {
+                    info.source = line
+                        .strip_prefix("source ")
+                        .ok_or_else(invalid)?
+                        .to_string()
+                }
+                Some("line") => info.lines.push(LineEntry {
+                    line: parse_item(items.next()).ok_or_else(invalid)?,
+                    ic: parse_item(items.next()).ok_or_else(invalid)?,
+                }),
+                Some("var") => info.variables.push(VariableInfo {
+                    name: items.next().ok_or_else(invalid)?.to_string(),
+                    address: parse_item(items.next()).ok_or_else(invalid)?,
+                    size: parse_item(items.next()).ok_or_else(invalid)?,
+                }),
+                Some("") => {}
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(info)
+    }
+
+    pub fn save(&self, path: &str) {
+        fs::write(path, self.to_text()).unwrap();
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("{path}: {err}"))?;
+        Self::from_text(&text)
+    }
+}
+
+/// Number of a debug info entry, `None` if it's missing or doesn't fit in `T`
+fn parse_item<T: FromStr>(item: Option<&str>) -> Option<T> {
+    item?.parse().ok()
 }
@@ -0,0 +1,35 @@
+use smol_file::{DebugInfo, LineEntry, VariableInfo};
+
+#[test]
+pub fn it_round_trips_through_text() {
+    let info = DebugInfo {
+        source: "/src/with space.smol".to_string(),
+        lines: vec![LineEntry { line: 4, ic: 0 }],
+        variables: vec![VariableInfo {
+            name: "msg".to_string(),
+            address: u16::MAX / 2,
+            size: 3,
+        }],
+    };
+
+    assert_eq!(DebugInfo::from_text(&info.to_text()), Ok(info));
+}
+
+#[test]
+pub fn it_rejects_a_source_without_a_path() {
+    assert_eq!(
+        DebugInfo::from_text("line 4 0\nsource"),
+        Err("Invalid debug info on line 2: 'source'".to_string())
+    );
+    assert!(DebugInfo::from_text("line 4").is_err());
+}
+
+#[test]
+pub fn it_rejects_numbers_that_dont_fit() {
+    assert_eq!(
+        DebugInfo::from_text("line 4 65536"),
+        Err("Invalid debug info on line 1: 'line 4 65536'".to_string())
+    );
+    assert!(DebugInfo::from_text("var msg 70000 3").is_err());
+    assert!(DebugInfo::from_text("var msg 0 65536").is_err());
+}
@@ -0,0 +1,2 @@
+mod debug_info_test;
+mod smol_file_test;
@@ -0,0 +1,30 @@
+use smol_file::SmolFile;
+
+#[test]
+pub fn it_loads_storage_and_instructions() {
+    let file = SmolFile::from_bytes(&[
+        // Storage size
+        4,
+        0,
+        // Uninitialised variable of 2 bytes at offset 0
+        2,
+        0,
+        0,
+        0,
+        // Instructions
+        0b11_101_111,
+    ])
+    .unwrap();
+
+    assert_eq!(file.storage.total_size, 4);
+    assert_eq!(file.instructions, [0b11_101_111]);
+}
+
+#[test]
+pub fn it_rejects_a_cut_off_storage() {
+    assert!(SmolFile::from_bytes(&[]).is_err());
+    assert!(SmolFile::from_bytes(&[16, 0, 0]).is_err());
+    // Initialised variable header without its offset
+    assert!(SmolFile::from_bytes(&[2, 0, 3, 0x80]).is_err());
+    assert!(SmolFile::try_load("/nonexistent.obj").is_err());
+}
@@ -0,0 +1,379 @@
+//! Single definition of the smol instruction set.
+//!
+//! Every instruction form is an entry of [INSTRUCTIONS] with its mnemonic, opcode bits
+//! and operands. The assembler encodes with it and the VM and disassembler decode with it,
+//! so they can't disagree on the layout of an instruction.
+//!
+//! An instruction is the opcode byte, followed by the register byte if an operand is
+//! a register, followed by the immediates in little endian.
+//! The top two bits of the opcode select the [Family].
+//! Register operands are the 4-bit encodings of [Register].
+//! [decode] also decodes the operands, so the VM and the disassembler
+//! agree on the meaning of an instruction too.
+
+// Opcodes are grouped by their fields
+#![allow(clippy::unusual_byte_groupings)]
+
+use std::fmt;
+
+mod operand;
+mod register;
+
+pub use operand::Operand;
+pub use register::Register;
+
+/// Instruction family, `opcode[0:1]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Family {
+    Alu,
+    LoadStore,
+    Stack,
+    Branch,
+}
+
+impl Family {
+    pub fn of(opcode: u8) -> Self {
+        match opcode >> 6 {
+            0b00 => Family::Alu,
+            0b01 => Family::LoadStore,
+            0b10 => Family::Stack,
+            _ => Family::Branch,
+        }
+    }
+}
+
+/// What the instruction does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Add,
+    Sub,
+    And,
+    Or,
+    Xor,
+    Not,
+    /// Equality, `cmp` is the no-op form
+    Eq,
+    Inc,
+    Dec,
+    Load,
+    Store,
+    Push,
+    Pop,
+    /// Move `sp` into the variable space
+    SaveVariable,
+    /// Move `sp` back to the stack
+    UnsaveVariable,
+    Jump,
+    BranchEqual,
+    BranchNotEqual,
+    BranchGreater,
+    BranchLess,
+    Call,
+    Return,
+    /// Return from interrupt, there are no interrupts
+    ReturnInterrupt,
+    Syscall,
+}
+
+/// How an operand is encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperandKind {
+    /// Register in the low nibble of the register byte
+    Register,
+    /// Register in the high nibble of the register byte
+    SourceRegister,
+    Immediate8,
+    Immediate16,
+    /// `sp` + 8 bit immediate
+    StackOffset,
+    /// 16 bit immediate address
+    Address,
+    /// `sp` + the 8 bit register in the high nibble of the register byte
+    StackRegister,
+    /// Address in the 16 bit register in the high nibble of the register byte
+    Pointer,
+    /// Signed 8 bit offset from the start of the instruction
+    Offset8,
+    /// Signed 16 bit offset from the start of the instruction
+    Offset16,
+}
+
+impl OperandKind {
+    /// The operand is a nibble of the register byte
+    pub fn in_register_byte(self) -> bool {
+        matches!(
+            self,
+            Self::Register | Self::SourceRegister | Self::StackRegister | Self::Pointer
+        )
+    }
+
+    /// Bytes of the immediate after the register byte
+    pub fn immediate_size(self) -> u16 {
+        match self {
+            Self::Immediate8 | Self::StackOffset | Self::Offset8 => 1,
+            Self::Immediate16 | Self::Address | Self::Offset16 => 2,
+            _ => 0,
+        }
+    }
+}
+
+/// Form of an instruction
+#[derive(Debug, PartialEq, Eq)]
+pub struct InstructionDef {
+    /// Mnemonic as written in the assembly
+    pub mnemonic: &'static str,
+    pub operation: Operation,
+    /// Opcode byte without modifier bits
+    pub opcode: u8,
+    /// Bits of the opcode that select this form, the others are modifiers or ignored
+    pub mask: u8,
+    /// The width bit is set, the instruction works on 16 bits or has a 16 bit offset
+    pub wide: bool,
+    pub operands: &'static [OperandKind],
+}
+
+/// Opcode bit of the ALU family to not write the result back, only the flags
+pub const ALU_NOOP: u8 = 0b1;
+
+/// Operation and source, the width and no-op bits are modifiers
+const ALU_MASK: u8 = 0b11_111_1_00;
+/// Equality and compare differ in the no-op bit
+const ALU_EQ_MASK: u8 = 0b11_111_1_01;
+/// Not has no source so the source bit is ignored
+const ALU_NOT_MASK: u8 = 0b11_111_0_00;
+const LOAD_STORE_MASK: u8 = 0b11_1_1_11_00;
+const STACK_MASK: u8 = 0b11_11_11_00;
+/// Unsave variable has no operand so the source bits are ignored
+const STACK_UV_MASK: u8 = 0b11_11_00_00;
+const BRANCH_MASK: u8 = 0b11_111_1_00;
+/// Returns have no offset so the width bit is ignored
+const BRANCH_RET_MASK: u8 = 0b11_111_0_00;
+
+use OperandKind::*;
+use Operation as Op;
+
+const fn def(
+    mnemonic: &'static str,
+    operation: Operation,
+    opcode: u8,
+    mask: u8,
+    wide: bool,
+    operands: &'static [OperandKind],
+) -> InstructionDef {
+    InstructionDef {
+        mnemonic,
+        operation,
+        opcode,
+        mask,
+        wide,
+        operands,
+    }
+}
+
+const REG_REG: &[OperandKind] = &[Register, SourceRegister];
+const REG_IMM: &[OperandKind] = &[Register, Immediate8];
+
+/// Every instruction form. Decoding takes the first form matching the opcode,
+/// so `syscall` comes before the 16 bit `call` it's carved out of.
+#[rustfmt::skip]
+pub const INSTRUCTIONS: &[InstructionDef] = &[
+    // ALU `0b00_ooo_s_w_n`, operation, immediate source, width and no-op
+    def("add", Op::Add, 0b00_000_0_0_0, ALU_MASK, false, REG_REG),
+    def("addi", Op::Add, 0b00_000_1_0_0, ALU_MASK, false, REG_IMM),
+    def("sub", Op::Sub, 0b00_001_0_0_0, ALU_MASK, false, REG_REG),
+    def("subi", Op::Sub, 0b00_001_1_0_0, ALU_MASK, false, REG_IMM),
+    def("and", Op::And, 0b00_010_0_0_0, ALU_MASK, false, REG_REG),
+    def("andi", Op::And, 0b00_010_1_0_0, ALU_MASK, false, REG_IMM),
+    def("or", Op::Or, 0b00_011_0_0_0, ALU_MASK, false, REG_REG),
+    def("ori", Op::Or, 0b00_011_1_0_0, ALU_MASK, false, REG_IMM),
+    def("xor", Op::Xor, 0b00_100_0_0_0, ALU_MASK, false, REG_REG),
+    def("xori", Op::Xor, 0b00_100_1_0_0, ALU_MASK, false, REG_IMM),
+    def("not", Op::Not, 0b00_101_0_0_0, ALU_NOT_MASK, false, &[Register]),
+    def("eq", Op::Eq, 0b00_110_0_0_0, ALU_EQ_MASK, false, REG_REG),
+    def("eqi", Op::Eq, 0b00_110_1_0_0, ALU_EQ_MASK, false, REG_IMM),
+    def("cmp", Op::Eq, 0b00_110_0_0_1, ALU_EQ_MASK, false, REG_REG),
+    def("cmpi", Op::Eq, 0b00_110_1_0_1, ALU_EQ_MASK, false, REG_IMM),
+    def("inc", Op::Inc, 0b00_111_0_0_0, ALU_MASK, false, &[Register]),
+    def("dec", Op::Dec, 0b00_111_1_0_0, ALU_MASK, false, &[Register]),
+    // LoadStore `0b01_d_w_mm_00`, direction, width and addressing mode
+    def("ldb", Op::Load, 0b01_0_0_00_00, LOAD_STORE_MASK, false, &[Register, StackOffset]),
+    def("ldb", Op::Load, 0b01_0_0_01_00, LOAD_STORE_MASK, false, &[Register, Address]),
+    def("ldb", Op::Load, 0b01_0_0_10_00, LOAD_STORE_MASK, false, &[Register, StackRegister]),
+    def("ldb", Op::Load, 0b01_0_0_11_00, LOAD_STORE_MASK, false, &[Register, Pointer]),
+    def("ldw", Op::Load, 0b01_0_1_00_00, LOAD_STORE_MASK, true, &[Register, StackOffset]),
+    def("ldw", Op::Load, 0b01_0_1_01_00, LOAD_STORE_MASK, true, &[Register, Address]),
+    def("ldw", Op::Load, 0b01_0_1_10_00, LOAD_STORE_MASK, true, &[Register, StackRegister]),
+    def("ldw", Op::Load, 0b01_0_1_11_00, LOAD_STORE_MASK, true, &[Register, Pointer]),
+    def("stb", Op::Store, 0b01_1_0_00_00, LOAD_STORE_MASK, false, &[Register, StackOffset]),
+    def("stb", Op::Store, 0b01_1_0_01_00, LOAD_STORE_MASK, false, &[Register, Address]),
+    def("stb", Op::Store, 0b01_1_0_10_00, LOAD_STORE_MASK, false, &[Register, StackRegister]),
+    def("stb", Op::Store, 0b01_1_0_11_00, LOAD_STORE_MASK, false, &[Register, Pointer]),
+    def("stw", Op::Store, 0b01_1_1_00_00, LOAD_STORE_MASK, true, &[Register, StackOffset]),
+    def("stw", Op::Store, 0b01_1_1_01_00, LOAD_STORE_MASK, true, &[Register, Address]),
+    def("stw", Op::Store, 0b01_1_1_10_00, LOAD_STORE_MASK, true, &[Register, StackRegister]),
+    def("stw", Op::Store, 0b01_1_1_11_00, LOAD_STORE_MASK, true, &[Register, Pointer]),
+    // Stack `0b10_oo_ss_00`, operation and source
+    def("push", Op::Push, 0b10_00_00_00, STACK_MASK, false, &[Register]),
+    def("push", Op::Push, 0b10_00_01_00, STACK_MASK, true, &[Register]),
+    def("push", Op::Push, 0b10_00_10_00, STACK_MASK, false, &[Immediate8]),
+    def("push", Op::Push, 0b10_00_11_00, STACK_MASK, true, &[Immediate16]),
+    // Pop can only be done into a register
+    def("pop", Op::Pop, 0b10_01_00_00, STACK_MASK, false, &[Register]),
+    def("pop", Op::Pop, 0b10_01_01_00, STACK_MASK, true, &[Register]),
+    def("sv", Op::SaveVariable, 0b10_10_00_00, STACK_MASK, false, &[Register]),
+    def("sv", Op::SaveVariable, 0b10_10_01_00, STACK_MASK, true, &[Register]),
+    def("sv", Op::SaveVariable, 0b10_10_10_00, STACK_MASK, false, &[Immediate8]),
+    def("sv", Op::SaveVariable, 0b10_10_11_00, STACK_MASK, true, &[Immediate16]),
+    def("uv", Op::UnsaveVariable, 0b10_11_00_00, STACK_UV_MASK, false, &[]),
+    // Branch `0b11_ooo_w_00`, operation and offset width
+    def("jmp", Op::Jump, 0b11_000_0_00, BRANCH_MASK, false, &[Offset8]),
+    def("jmp", Op::Jump, 0b11_000_1_00, BRANCH_MASK, true, &[Offset16]),
+    def("beq", Op::BranchEqual, 0b11_001_0_00, BRANCH_MASK, false, &[Offset8]),
+    def("beq", Op::BranchEqual, 0b11_001_1_00, BRANCH_MASK, true, &[Offset16]),
+    def("bne", Op::BranchNotEqual, 0b11_010_0_00, BRANCH_MASK, false, &[Offset8]),
+    def("bne", Op::BranchNotEqual, 0b11_010_1_00, BRANCH_MASK, true, &[Offset16]),
+    def("bgt", Op::BranchGreater, 0b11_011_0_00, BRANCH_MASK, false, &[Offset8]),
+    def("bgt", Op::BranchGreater, 0b11_011_1_00, BRANCH_MASK, true, &[Offset16]),
+    def("blt", Op::BranchLess, 0b11_100_0_00, BRANCH_MASK, false, &[Offset8]),
+    def("blt", Op::BranchLess, 0b11_100_1_00, BRANCH_MASK, true, &[Offset16]),
+    def("syscall", Op::Syscall, 0b11_101_1_11, 0b11_111_1_11, false, &[]),
+    def("call", Op::Call, 0b11_101_0_00, BRANCH_MASK, false, &[Offset8]),
+    def("call", Op::Call, 0b11_101_1_00, BRANCH_MASK, true, &[Offset16]),
+    def("ret", Op::Return, 0b11_110_0_00, BRANCH_RET_MASK, false, &[]),
+    def("reti", Op::ReturnInterrupt, 0b11_111_0_00, BRANCH_RET_MASK, false, &[]),
+];
+
+impl InstructionDef {
+    pub fn family(&self) -> Family {
+        Family::of(self.opcode)
+    }
+
+    /// The opcode byte is this form
+    pub fn matches(&self, opcode: u8) -> bool {
+        opcode & self.mask == self.opcode
+    }
+
+    fn has_register_byte(&self) -> bool {
+        self.operands.iter().any(|kind| kind.in_register_byte())
+    }
+
+    /// Size in bytes including the operands
+    pub fn size(&self) -> u16 {
+        let immediates: u16 = self.operands.iter().map(|kind| kind.immediate_size()).sum();
+        1 + self.has_register_byte() as u16 + immediates
+    }
+
+    /// Encode the instruction, `values` are in the order of [InstructionDef::operands].
+    /// Registers are given by their encoding and offsets as their 16 bit two's complement.
+    pub fn encode(&self, values: &[u16]) -> Vec<u8> {
+        assert_eq!(
+            values.len(),
+            self.operands.len(),
+            "'{}' takes {} operands",
+            self.mnemonic,
+            self.operands.len()
+        );
+        let mut bytes = vec![self.opcode];
+        if self.has_register_byte() {
+            bytes.push(0);
+        }
+        for (&kind, &value) in self.operands.iter().zip(values) {
+            match kind {
+                Register => bytes[1] |= value as u8 & 0b1111,
+                SourceRegister | StackRegister | Pointer => bytes[1] |= (value as u8 & 0b1111) << 4,
+                _ if kind.immediate_size() == 1 => bytes.push(value as u8),
+                _ => bytes.extend(value.to_le_bytes()),
+            }
+        }
+        bytes
+    }
+}
+
+/// Form of the opcode, `None` if no instruction has this opcode
+pub fn lookup(opcode: u8) -> Option<&'static InstructionDef> {
+    INSTRUCTIONS.iter().find(|def| def.matches(opcode))
+}
+
+/// Form with the mnemonic, width and operands, for encoding
+pub fn find(
+    mnemonic: &str,
+    wide: bool,
+    operands: &[OperandKind],
+) -> Option<&'static InstructionDef> {
+    INSTRUCTIONS
+        .iter()
+        .find(|def| def.mnemonic == mnemonic && def.wide == wide && def.operands == operands)
+}
+
+/// Why bytes don't decode into an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// No instruction has this opcode
+    InvalidOpcode,
+    /// The bytes end before the operands of the instruction
+    Truncated,
+    /// The register encoding is not mapped to a register
+    InvalidRegister(u8),
+}
+
+/// Most operands an instruction has
+pub const MAX_OPERANDS: usize = 2;
+
+/// Largest instruction in bytes, see [InstructionDef::size]
+pub const MAX_SIZE: usize = 4;
+
+/// Instruction decoded from bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decoded {
+    pub def: &'static InstructionDef,
+    /// Opcode byte including the modifier bits
+    pub opcode: u8,
+    /// Operand values in the order of [InstructionDef::operands], see [InstructionDef::encode]
+    pub values: [u16; MAX_OPERANDS],
+    /// Operands in the order of [InstructionDef::operands], `None` past the last one
+    pub operands: [Option<Operand>; MAX_OPERANDS],
+}
+
+impl fmt::Display for Decoded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.def.mnemonic)?;
+        for operand in self.operands.iter().flatten() {
+            write!(f, " {operand}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Decode the instruction at the start of `bytes`, fails on unmapped registers
+pub fn decode(bytes: &[u8]) -> Result<Decoded, DecodeError> {
+    let opcode = *bytes.first().ok_or(DecodeError::Truncated)?;
+    let def = lookup(opcode).ok_or(DecodeError::InvalidOpcode)?;
+    if bytes.len() < def.size() as usize {
+        return Err(DecodeError::Truncated);
+    }
+
+    let registers = bytes.get(1).copied().unwrap_or_default();
+    let mut next = 1 + def.has_register_byte() as usize;
+    let mut values = [0; MAX_OPERANDS];
+    for (value, &kind) in values.iter_mut().zip(def.operands) {
+        *value = match kind {
+            Register => (registers & 0b1111) as u16,
+            SourceRegister | StackRegister | Pointer => (registers >> 4) as u16,
+            // Sign extended so every offset is a 16 bit two's complement
+            Offset8 => bytes[next] as i8 as u16,
+            _ if kind.immediate_size() == 1 => bytes[next] as u16,
+            _ => u16::from_le_bytes([bytes[next], bytes[next + 1]]),
+        };
+        next += kind.immediate_size() as usize;
+    }
+    let mut operands = [None; MAX_OPERANDS];
+    for ((operand, &kind), &value) in operands.iter_mut().zip(def.operands).zip(&values) {
+        *operand = Some(Operand::decode(kind, value)?);
+    }
+    Ok(Decoded {
+        def,
+        opcode,
+        values,
+        operands,
+    })
+}
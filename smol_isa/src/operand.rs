@@ -0,0 +1,54 @@
+use std::fmt;
+
+use crate::{DecodeError, OperandKind, Register};
+
+/// Decoded operand of an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Register(Register),
+    Immediate8(u8),
+    Immediate16(u16),
+    /// Branch offset relative to the start of the instruction
+    Offset(i16),
+    /// `sp` + 8 bit immediate
+    StackOffset(u8),
+    /// `sp` + 8 bit register
+    StackRegister(Register),
+    /// Immediate memory address
+    Address(u16),
+    /// 16 bit register used as a memory address
+    Pointer(Register),
+}
+
+impl Operand {
+    /// Operand of the kind with the value as given to [crate::InstructionDef::encode],
+    /// fails on unmapped registers
+    pub fn decode(kind: OperandKind, value: u16) -> Result<Self, DecodeError> {
+        let register = || {
+            Register::from_encoding(value as u8).ok_or(DecodeError::InvalidRegister(value as u8))
+        };
+        Ok(match kind {
+            OperandKind::Register | OperandKind::SourceRegister => Self::Register(register()?),
+            OperandKind::Immediate8 => Self::Immediate8(value as u8),
+            OperandKind::Immediate16 => Self::Immediate16(value),
+            OperandKind::StackOffset => Self::StackOffset(value as u8),
+            OperandKind::Address => Self::Address(value),
+            OperandKind::StackRegister => Self::StackRegister(register()?),
+            OperandKind::Pointer => Self::Pointer(register()?),
+            OperandKind::Offset8 | OperandKind::Offset16 => Self::Offset(value as i16),
+        })
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Register(reg) | Self::Pointer(reg) => write!(f, "{reg}"),
+            Self::Immediate8(value) => write!(f, "{value}"),
+            Self::Immediate16(value) | Self::Address(value) => write!(f, "{value}"),
+            Self::Offset(offset) => write!(f, "{offset:+}"),
+            Self::StackOffset(offset) => write!(f, "sp+{offset}"),
+            Self::StackRegister(reg) => write!(f, "sp+{reg}"),
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Register by its 4-bit encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum Register {
+    /// 0th 8-bit general
+    R0 = 0b0000,
+    /// 1st 8-bit general
+    R1 = 0b0001,
+    /// 2nd 8-bit general
+    R2 = 0b0010,
+    /// 3rd 8-bit general
+    R3 = 0b0011,
+    /// 4th 8-bit general
+    R4 = 0b0100,
+    /// 5th 8-bit general
+    R5 = 0b0101,
+    /// 6th 8-bit general
+    R6 = 0b0110,
+    /// 7th 8-bit general
+    R7 = 0b0111,
+    /// 0th 16-bit general
+    L0 = 0b1001,
+    /// 1st 16-bit general
+    L1 = 0b1010,
+    /// Instruction Counter
+    Ic = 0b1011,
+    /// Core Flags
+    Fg = 0b1100,
+    /// Call Register
+    Cr = 0b1101,
+    /// Stack pointer
+    Sp = 0b1110,
+    /// Zero register (n-bits wide)
+    Zr = 0b1111,
+}
+
+impl Register {
+    /// Every register in the order of their encoding
+    pub const ALL: [Register; 15] = [
+        Register::R0,
+        Register::R1,
+        Register::R2,
+        Register::R3,
+        Register::R4,
+        Register::R5,
+        Register::R6,
+        Register::R7,
+        Register::L0,
+        Register::L1,
+        Register::Ic,
+        Register::Fg,
+        Register::Cr,
+        Register::Sp,
+        Register::Zr,
+    ];
+
+    /// Register of the 4-bit encoding, `0b1000` is reserved
+    pub fn from_encoding(encoding: u8) -> Option<Register> {
+        match encoding {
+            0b0000..=0b0111 => Some(Register::ALL[encoding as usize]),
+            0b1001..=0b1111 => Some(Register::ALL[encoding as usize - 1]),
+            _ => None,
+        }
+    }
+
+    /// 4-bit encoding of the register, also its index in the register file
+    pub fn encoding(self) -> u8 {
+        self as u8
+    }
+
+    /// Name of the register as written in the assembly
+    pub fn name(self) -> &'static str {
+        match self {
+            Register::Ic => "ic",
+            Register::Fg => "fg",
+            Register::Cr => "cr",
+            Register::Sp => "sp",
+            Register::Zr => "zr",
+            Register::R0 => "r0",
+            Register::R1 => "r1",
+            Register::R2 => "r2",
+            Register::R3 => "r3",
+            Register::R4 => "r4",
+            Register::R5 => "r5",
+            Register::R6 => "r6",
+            Register::R7 => "r7",
+            Register::L0 => "l0",
+            Register::L1 => "l1",
+        }
+    }
+
+    /// If the register is 16-bit wide, otherwise it's 8-bit
+    pub fn is_16b(self) -> bool {
+        !matches!(
+            self,
+            Register::R0
+                | Register::R1
+                | Register::R2
+                | Register::R3
+                | Register::R4
+                | Register::R5
+                | Register::R6
+                | Register::R7
+        )
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Register {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Register::ALL
+            .into_iter()
+            .find(|reg| reg.name() == s.trim())
+            .ok_or_else(|| format!("Unknown register '{s}'"))
+    }
+}
@@ -0,0 +1,2 @@
+mod register_test;
+mod table_test;
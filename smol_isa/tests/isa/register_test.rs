@@ -0,0 +1,22 @@
+use smol_isa::Register;
+
+#[test]
+pub fn it_decodes_every_register_encoding_to_itself() {
+    for register in Register::ALL {
+        assert_eq!(Register::from_encoding(register.encoding()), Some(register));
+        assert_eq!(register.name().parse(), Ok(register));
+    }
+    assert_eq!(Register::from_encoding(0b1000), None);
+    assert_eq!(Register::from_encoding(0b1_0000), None);
+}
+
+#[test]
+pub fn it_only_has_8_bit_general_registers_below_the_reserved_encoding() {
+    for register in Register::ALL {
+        assert_eq!(
+            register.is_16b(),
+            register.encoding() > 0b1000,
+            "{register}"
+        );
+    }
+}
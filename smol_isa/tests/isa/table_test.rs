@@ -0,0 +1,104 @@
+use smol_isa::{decode, find, lookup, DecodeError, Family, OperandKind, INSTRUCTIONS, MAX_SIZE};
+
+#[test]
+pub fn it_decodes_every_form_to_itself() {
+    for def in INSTRUCTIONS {
+        assert_eq!(lookup(def.opcode), Some(def), "{}", def.mnemonic);
+        assert_eq!(def.opcode & def.mask, def.opcode, "{}", def.mnemonic);
+        assert!(def.size() as usize <= MAX_SIZE, "{}", def.mnemonic);
+    }
+}
+
+#[test]
+pub fn it_finds_every_form_by_mnemonic_and_operands() {
+    for def in INSTRUCTIONS {
+        assert_eq!(find(def.mnemonic, def.wide, def.operands), Some(def));
+    }
+}
+
+#[test]
+pub fn it_only_overlaps_syscall_with_call() {
+    for opcode in 0..=u8::MAX {
+        let matching: Vec<&str> = INSTRUCTIONS
+            .iter()
+            .filter(|def| def.matches(opcode))
+            .map(|def| def.mnemonic)
+            .collect();
+        match opcode {
+            0b11_101_1_11 => assert_eq!(matching, ["syscall", "call"]),
+            _ => assert!(matching.len() <= 1, "{opcode:#010b} is {matching:?}"),
+        }
+    }
+}
+
+#[test]
+pub fn it_round_trips_every_form() {
+    for def in INSTRUCTIONS {
+        let values: Vec<u16> = def
+            .operands
+            .iter()
+            .map(|kind| match kind {
+                OperandKind::Register => 0b1001,
+                OperandKind::SourceRegister | OperandKind::StackRegister | OperandKind::Pointer => {
+                    0b0011
+                }
+                OperandKind::Immediate8 | OperandKind::StackOffset => 0xab,
+                OperandKind::Offset8 => -3_i16 as u16,
+                _ => 0x1234,
+            })
+            .collect();
+        let bytes = def.encode(&values);
+        let decoded = decode(&bytes).unwrap();
+
+        assert_eq!(bytes.len(), def.size() as usize, "{}", def.mnemonic);
+        assert_eq!(decoded.def, def);
+        assert_eq!(decoded.values[..values.len()], values);
+        assert_eq!(
+            decoded.operands.iter().flatten().count(),
+            def.operands.len()
+        );
+        assert_eq!(Family::of(bytes[0]), def.family());
+    }
+}
+
+#[test]
+pub fn it_encodes_the_documented_layouts() {
+    let stw = find("stw", true, &[OperandKind::Register, OperandKind::Address]).unwrap();
+    assert_eq!(
+        stw.encode(&[0b1001, 0x8000]),
+        [0b01_1_1_01_00, 0b0000_1001, 0x00, 0x80]
+    );
+
+    let add = find(
+        "add",
+        false,
+        &[OperandKind::Register, OperandKind::SourceRegister],
+    )
+    .unwrap();
+    assert_eq!(add.encode(&[2, 5]), [0b00_000_0_0_0, 0b0101_0010]);
+
+    let sv = find("sv", true, &[OperandKind::Immediate16]).unwrap();
+    assert_eq!(sv.encode(&[3]), [0b10_10_11_00, 3, 0]);
+
+    assert_eq!(find("uv", false, &[]).unwrap().encode(&[]), [0b10_11_00_00]);
+    assert_eq!(
+        find("syscall", false, &[]).unwrap().encode(&[]),
+        [0b11_101_111]
+    );
+}
+
+#[test]
+pub fn it_rejects_invalid_bytes() {
+    // Pop into an immediate
+    assert_eq!(decode(&[0b10_01_10_00, 0]), Err(DecodeError::InvalidOpcode));
+    // Branch with only half of its 16 bit offset
+    assert_eq!(decode(&[0b11_000_1_00, 0]), Err(DecodeError::Truncated));
+    assert_eq!(decode(&[]), Err(DecodeError::Truncated));
+    // ALU Add from Register with the unmapped register 0b1000
+    assert_eq!(
+        decode(&[0b00_000_0_0_0, 0b0000_1000]),
+        Err(DecodeError::InvalidRegister(0b1000))
+    );
+    // Modifier bits don't change the form
+    assert_eq!(decode(&[0b00_000_0_1_1, 0]).unwrap().def.mnemonic, "add");
+}
@@ -0,0 +1,3 @@
+#![allow(clippy::unusual_byte_groupings)]
+
+mod isa;
@@ -0,0 +1,78 @@
+//! Compares running a tight loop with and without the decode cache,
+//! and with the history that records every step.
+//!
+//! `cargo bench -p smol-vm --bench decode_cache`
+
+#![allow(clippy::unusual_byte_groupings)]
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use smol_vm::Vm;
+
+const RUNS: u32 = 20;
+
+/// Counts r1 through 256 values 200 times, about 150k instructions
+fn counting_loop() -> Vec<u8> {
+    vec![
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r1
+        0b0000_0001,
+        // ALU Equality from Immediate without writing the result
+        0b00_110_1_0_1,
+        // Register r1
+        0b0000_0001,
+        // Immediate 0
+        0,
+        // Branch if not equal 8 bit offset
+        0b11_010_0_00,
+        // Back to the increment of r1
+        -5_i8 as u8,
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r2
+        0b0000_0010,
+        // ALU Equality from Immediate without writing the result
+        0b00_110_1_0_1,
+        // Register r2
+        0b0000_0010,
+        // Immediate 200
+        200,
+        // Branch if not equal 8 bit offset
+        0b11_010_0_00,
+        // Back to the start
+        -12_i8 as u8,
+    ]
+}
+
+/// Fastest of [RUNS] runs
+fn bench(decode_cache: bool, history: bool) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let mut vm = Vm::default();
+            vm.set_decode_cache(decode_cache);
+            vm.set_history(history);
+            vm.instructions.instructions = counting_loop();
+
+            let start = Instant::now();
+            black_box(vm.run().unwrap());
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let uncached = bench(false, false);
+    let cached = bench(true, false);
+    let recorded = bench(true, true);
+
+    println!("decode every instruction: {uncached:?}");
+    println!("decode cache:             {cached:?}");
+    println!(
+        "speedup:                  {:.2}x",
+        uncached.as_secs_f64() / cached.as_secs_f64()
+    );
+    println!("decode cache and history: {recorded:?}");
+}
@@ -0,0 +1,13 @@
+use std::{io, process::exit};
+
+use smol_vm::dap::DapServer;
+
+/// Debug adapter for editors, the program to debug comes from the `launch` request
+fn main() {
+    let mut input = io::stdin().lock();
+    let mut output = io::stdout().lock();
+    if let Err(err) = DapServer::new().serve(&mut input, &mut output) {
+        eprintln!("{err}");
+        exit(1);
+    }
+}
@@ -0,0 +1,350 @@
+use std::{
+    io::{self, BufRead, Write},
+    ops::Range,
+    process::exit,
+};
+
+use smol_isa::Decoded;
+use smol_vm::{
+    syscall, Condition, ExitReason, Register, StopReason, Vm, VmErrorKind, WatchKind, Watchpoint,
+};
+
+const HELP: &str = "\
+commands:
+  s, step [n]                  execute n instructions
+  back [n]                     undo n instructions
+  c, continue                  run until a breakpoint, watchpoint or the end
+  b, break <ic> [if <cond>]    break before ic, e.g. `b 12 if r0 == 60`
+  delete <ic>                  remove the breakpoint at ic
+  watch <addr> [len] [r|w|rw]  stop after memory in addr..addr+len is accessed
+  unwatch <addr> [len] [r|w|rw]
+  set <reg> <value>            write a register
+  write <addr> <byte>...       write bytes into memory
+  r, regs                      show the registers
+  stack                        hex dump around sp
+  vars                         hex dump of the variables
+  x <addr> [len]               hex dump of memory
+  l, list                      disassembly around ic
+  info                         list breakpoints and watchpoints
+  q, quit";
+
+/// Decimal or `0x` hexadecimal number
+fn parse_number(s: &str) -> Result<u16, String> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|_| format!("Invalid number '{s}'"))
+}
+
+fn parse_watchpoint(args: &[&str]) -> Result<Watchpoint, String> {
+    let start = parse_number(args.first().ok_or("Missing address")?)?;
+    let len = args.get(1).map_or(Ok(1), |len| parse_number(len))?;
+    let kind = match args.get(2).copied().unwrap_or("w") {
+        "r" => WatchKind::Read,
+        "w" => WatchKind::Write,
+        "rw" => WatchKind::ReadWrite,
+        kind => return Err(format!("Unknown watch kind '{kind}'")),
+    };
+    let end = start
+        .checked_add(len)
+        .ok_or_else(|| format!("{start}+{len} is outside of the memory"))?;
+    Ok(Watchpoint::new(start..end, kind))
+}
+
+/// `ic` of an instruction that can't be executed and why
+type Fault = (u16, VmErrorKind);
+
+struct Debugger {
+    vm: Vm,
+    /// Memory used by the variables of the file
+    variables: Range<usize>,
+}
+
+impl Debugger {
+    fn print_location(&self) {
+        let ic = self.vm.registers[Register::Ic];
+        if self.vm.is_finished() {
+            println!("program finished at ic {ic}");
+            return;
+        }
+        let bytes = self.vm.instructions.instructions.get(ic as usize..);
+        match smol_isa::decode(bytes.unwrap_or_default()) {
+            Ok(instruction) => println!("=> {ic:5}: {instruction}"),
+            Err(err) => println!("=> {ic:5}: {}", VmErrorKind::from(err)),
+        }
+    }
+
+    fn print_registers(&self) {
+        for (idx, register) in Register::ALL.into_iter().enumerate() {
+            let value = self.vm.registers.get(register);
+            if register.is_16b() {
+                print!("{register:>3} = {value:#06x} ({value:5})");
+            } else {
+                print!("{register:>3} = {value:#04x}   ({value:5})");
+            }
+            print!("{}", if idx % 4 == 3 { "\n" } else { "   " });
+        }
+        println!();
+    }
+
+    fn hex_dump(&self, range: Range<usize>) {
+        let memory = self.vm.stack.memory();
+        let range = range.start.min(memory.len())..range.end.min(memory.len());
+        let sp = self.vm.registers[Register::Sp] as usize;
+        for row in (range.start & !0xf..range.end).step_by(16) {
+            let bytes = &memory[row..(row + 16).min(memory.len())];
+            print!("{row:#06x}:");
+            for (address, byte) in (row..).zip(bytes) {
+                let marker = if address == sp { '>' } else { ' ' };
+                print!("{marker}{byte:02x}");
+            }
+            let ascii: String = bytes
+                .iter()
+                .map(|&byte| match byte {
+                    0x20..=0x7e => byte as char,
+                    _ => '.',
+                })
+                .collect();
+            println!("  {ascii}");
+        }
+    }
+
+    /// Every instruction by its `ic`, decoded from the start like the VM would reach them.
+    /// Stops at the first one that doesn't decode or ends past the 64kib `ic` can address.
+    fn disassemble(&self) -> (Vec<(u16, Decoded)>, Option<Fault>) {
+        let code = &self.vm.instructions.instructions;
+        let mut instructions = Vec::new();
+        let mut ic = 0_u16;
+        while (ic as usize) < code.len() {
+            let decoded = match smol_isa::decode(&code[ic as usize..]) {
+                Ok(decoded) => decoded,
+                Err(err) => return (instructions, Some((ic, err.into()))),
+            };
+            let Some(next) = ic.checked_add(decoded.def.size()) else {
+                return (instructions, Some((ic, VmErrorKind::InstructionOutOfRange)));
+            };
+            instructions.push((ic, decoded));
+            ic = next;
+        }
+        (instructions, None)
+    }
+
+    fn list(&self) {
+        let ic = self.vm.registers[Register::Ic];
+        let (instructions, error) = self.disassemble();
+        let current = instructions
+            .iter()
+            .position(|(at, _)| *at >= ic)
+            .unwrap_or(instructions.len());
+        let start = current.saturating_sub(4);
+        for (at, instruction) in instructions.iter().skip(start).take(9) {
+            let marker = if *at == ic { "=>" } else { "  " };
+            let end = *at as usize + instruction.def.size() as usize;
+            let bytes = &self.vm.instructions.instructions[*at as usize..end];
+            let bytes: Vec<String> = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+            let breakpoint = self
+                .vm
+                .breakpoints()
+                .iter()
+                .any(|(breakpoint, _)| breakpoint == at);
+            println!(
+                "{marker}{}{at:5}: {:<12} {instruction}",
+                if breakpoint { '*' } else { ' ' },
+                bytes.join(" ")
+            );
+        }
+        if let Some((at, err)) = error {
+            if current + 5 >= instructions.len() {
+                println!("   {at:5}: {err}");
+            }
+        }
+    }
+
+    fn print_exit(&self, reason: ExitReason) {
+        match reason {
+            ExitReason::EndOfInstructions => println!("end of instructions"),
+            ExitReason::Exit(code) => println!("program exited with code {code}"),
+            ExitReason::Stopped {
+                reason: StopReason::Breakpoint(ic),
+            } => println!("breakpoint at {ic}"),
+            ExitReason::Stopped {
+                reason: StopReason::Watchpoint { access, .. },
+            } => {
+                let (address, len) = access.range();
+                println!("watchpoint: {access:?} of {len} bytes at {address:#06x}");
+            }
+            ExitReason::OutOfFuel | ExitReason::Timeout => println!("{reason:?}"),
+        }
+        self.print_location();
+    }
+
+    fn step(&mut self, count: u16) -> Result<(), String> {
+        for _ in 0..count {
+            match self.vm.step().map_err(|err| err.to_string())? {
+                Some(step) => {
+                    let changes: Vec<String> = step
+                        .registers_written
+                        .iter()
+                        .map(|write| format!("{} {} -> {}", write.register, write.old, write.new))
+                        .collect();
+                    println!("{:5}: {step}  {}", step.ic, changes.join(", "));
+                }
+                None => break,
+            }
+        }
+        self.print_location();
+        Ok(())
+    }
+
+    fn back(&mut self, count: u16) {
+        for _ in 0..count {
+            if self.vm.step_back().is_none() {
+                println!("start of the history");
+                break;
+            }
+        }
+        self.print_location();
+    }
+
+    /// Returns false once the debugger should quit
+    fn command(&mut self, line: &str) -> Result<bool, String> {
+        let args: Vec<&str> = line.split_whitespace().collect();
+        let Some((&command, args)) = args.split_first() else {
+            return Ok(true);
+        };
+        let count = || args.first().map_or(Ok(1), |n| parse_number(n));
+
+        match command {
+            "s" | "step" => self.step(count()?)?,
+            "back" => self.back(count()?),
+            "c" | "continue" => {
+                let reason = self.vm.run().map_err(|err| err.to_string())?;
+                self.print_exit(reason);
+            }
+            "b" | "break" => {
+                let ic = parse_number(args.first().ok_or("Missing ic")?)?;
+                match args.get(1) {
+                    Some(&"if") => {
+                        let condition: Condition = args[2..].join(" ").parse()?;
+                        self.vm.add_conditional_breakpoint(ic, condition);
+                    }
+                    Some(arg) => return Err(format!("Unexpected '{arg}'")),
+                    None => self.vm.add_breakpoint(ic),
+                }
+            }
+            "delete" => {
+                let ic = parse_number(args.first().ok_or("Missing ic")?)?;
+                if !self.vm.remove_breakpoint(ic) {
+                    return Err(format!("No breakpoint at {ic}"));
+                }
+            }
+            "watch" => self.vm.add_watchpoint(parse_watchpoint(args)?),
+            "unwatch" => {
+                if !self.vm.remove_watchpoint(&parse_watchpoint(args)?) {
+                    return Err("No such watchpoint".to_string());
+                }
+            }
+            "set" => {
+                let register: Register = args.first().ok_or("Missing register")?.parse()?;
+                let value = parse_number(args.get(1).ok_or("Missing value")?)?;
+                self.vm.registers.set(register, value);
+            }
+            "write" => {
+                let address = parse_number(args.first().ok_or("Missing address")?)? as usize;
+                let bytes = args[1..]
+                    .iter()
+                    .map(|byte| u8::try_from(parse_number(byte)?).map_err(|err| err.to_string()))
+                    .collect::<Result<Vec<u8>, String>>()?;
+                let memory = self
+                    .vm
+                    .stack
+                    .memory_mut()
+                    .get_mut(address..address + bytes.len())
+                    .ok_or("Write is outside of the memory")?;
+                memory.copy_from_slice(&bytes);
+            }
+            "r" | "regs" => self.print_registers(),
+            "stack" => {
+                let sp = self.vm.registers[Register::Sp] as usize;
+                self.hex_dump(sp.saturating_sub(32)..sp + 32);
+            }
+            "vars" => self.hex_dump(self.variables.clone()),
+            "x" => {
+                let address = parse_number(args.first().ok_or("Missing address")?)? as usize;
+                let len = args.get(1).map_or(Ok(16), |len| parse_number(len))? as usize;
+                self.hex_dump(address..address + len);
+            }
+            "l" | "list" => self.list(),
+            "info" => {
+                for (ic, condition) in self.vm.breakpoints() {
+                    match condition {
+                        Some(condition) => println!("breakpoint {ic} if {condition}"),
+                        None => println!("breakpoint {ic}"),
+                    }
+                }
+                for watchpoint in self.vm.watchpoints() {
+                    println!(
+                        "watchpoint {:#06x}..{:#06x} {:?}",
+                        watchpoint.addresses.start, watchpoint.addresses.end, watchpoint.kind
+                    );
+                }
+            }
+            "h" | "help" => println!("{HELP}"),
+            "q" | "quit" => return Ok(false),
+            _ => return Err(format!("Unknown command '{command}', see `help`")),
+        }
+        Ok(true)
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() < 2 {
+        println!("Give file as an argument");
+        exit(1);
+    }
+
+    let file = match smol_file::SmolFile::try_load(&args[1]) {
+        Ok(file) => file,
+        Err(err) => {
+            eprintln!("Failed to load {err}");
+            exit(1);
+        }
+    };
+    let start = (u16::MAX / 2) as usize;
+    let end = file
+        .storage
+        .items
+        .iter()
+        .map(|item| item.offset as usize + item.size as usize)
+        .max()
+        .unwrap_or(start);
+
+    let mut vm = Vm::with_syscalls(syscall::host());
+    vm.set_history(true);
+    if let Err(err) = vm.load_program(file) {
+        eprintln!("Failed to load {}: {err}", args[1]);
+        exit(1);
+    }
+    let mut debugger = Debugger {
+        vm,
+        variables: start..end,
+    };
+    debugger.print_location();
+
+    let stdin = io::stdin();
+    loop {
+        print!("(smol) ");
+        io::stdout().flush().unwrap();
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap() == 0 {
+            break;
+        }
+        match debugger.command(&line) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(err) => println!("{err}"),
+        }
+    }
+}
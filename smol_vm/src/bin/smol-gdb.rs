@@ -0,0 +1,88 @@
+use std::{
+    io::{self, Read, Write},
+    net::TcpListener,
+    process::exit,
+};
+
+use smol_vm::{gdb::GdbStub, syscall, Vm};
+
+/// stdin and stdout as a single connection, for `target remote | smol-gdb --stdio file.obj`
+struct Stdio;
+
+impl Read for Stdio {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        io::stdin().read(buf)
+    }
+}
+
+impl Write for Stdio {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        io::stdout().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().collect();
+    // Talk over stdin/stdout instead of a TCP port
+    let stdio = args.iter().any(|arg| arg == "--stdio");
+    args.retain(|arg| arg != "--stdio");
+    let port = match args.iter().position(|arg| arg == "--port") {
+        Some(idx) if idx + 1 < args.len() => {
+            args.remove(idx);
+            args.remove(idx).parse().unwrap_or_else(|_| {
+                println!("--port needs a port number");
+                exit(1);
+            })
+        }
+        Some(_) => {
+            println!("--port needs a port number");
+            exit(1);
+        }
+        None => 1234,
+    };
+    if args.len() < 2 {
+        println!("Give file as an argument");
+        exit(1);
+    }
+
+    let file = match smol_file::SmolFile::try_load(&args[1]) {
+        Ok(file) => file,
+        Err(err) => {
+            eprintln!("Failed to load {err}");
+            exit(1);
+        }
+    };
+    // Program output would corrupt the packets on stdout,
+    // it's captured and printed to stderr at the end of the session instead
+    let sandbox = syscall::Sandbox::new();
+    let syscalls = if stdio {
+        Box::new(sandbox.clone())
+    } else {
+        syscall::host()
+    };
+    let mut vm = Vm::with_syscalls(syscalls);
+    if let Err(err) = vm.load_program(file) {
+        eprintln!("Failed to load {}: {err}", args[1]);
+        exit(1);
+    }
+    let mut stub = GdbStub::new(vm);
+
+    let result = if stdio {
+        stub.serve(&mut Stdio)
+    } else {
+        let listener = TcpListener::bind(("127.0.0.1", port)).unwrap();
+        eprintln!("Waiting for gdb on 127.0.0.1:{port}");
+        let (mut stream, _) = listener.accept().unwrap();
+        stub.serve(&mut stream)
+    };
+    io::stderr().write_all(&sandbox.stdout()).unwrap();
+    io::stderr().write_all(&sandbox.stderr()).unwrap();
+    if let Err(err) = result {
+        eprintln!("{err}");
+        exit(1);
+    }
+}
@@ -0,0 +1,207 @@
+use std::{fmt, ops::Range, str::FromStr};
+
+use crate::{MemoryAccess, Register, Registers, Step, Vm};
+
+/// Comparison of a [Condition]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    const ALL: [Comparison; 6] = [
+        Comparison::Eq,
+        Comparison::Ne,
+        Comparison::Le,
+        Comparison::Ge,
+        Comparison::Lt,
+        Comparison::Gt,
+    ];
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Comparison::Eq => "==",
+            Comparison::Ne => "!=",
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+        }
+    }
+}
+
+/// Condition of a breakpoint comparing a register to a value, e.g. `r0 == 60`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Condition {
+    pub register: Register,
+    pub comparison: Comparison,
+    pub value: u16,
+}
+
+impl Condition {
+    pub fn new(register: Register, comparison: Comparison, value: u16) -> Self {
+        Self {
+            register,
+            comparison,
+            value,
+        }
+    }
+
+    pub fn holds(&self, registers: &Registers) -> bool {
+        let left = registers.get(self.register);
+        match self.comparison {
+            Comparison::Eq => left == self.value,
+            Comparison::Ne => left != self.value,
+            Comparison::Lt => left < self.value,
+            Comparison::Le => left <= self.value,
+            Comparison::Gt => left > self.value,
+            Comparison::Ge => left >= self.value,
+        }
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.register,
+            self.comparison.symbol(),
+            self.value
+        )
+    }
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    /// Parses `<register> <comparison> <value>`, the value is decimal or `0x` hexadecimal
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Two character comparisons first so `<=` isn't taken for `<`
+        let (idx, comparison) = Comparison::ALL
+            .into_iter()
+            .find_map(|comparison| Some((s.find(comparison.symbol())?, comparison)))
+            .ok_or_else(|| format!("Missing comparison in '{s}'"))?;
+        let register = s[..idx].parse()?;
+        let value = s[idx + comparison.symbol().len()..].trim();
+        let value = match value.strip_prefix("0x") {
+            Some(hex) => u16::from_str_radix(hex, 16),
+            None => value.parse(),
+        }
+        .map_err(|_| format!("Invalid value '{value}'"))?;
+        Ok(Self::new(register, comparison, value))
+    }
+}
+
+/// Memory accesses a [Watchpoint] triggers on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+/// Stops [Vm::run] after an instruction accessed memory in the address range
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub addresses: Range<u16>,
+    pub kind: WatchKind,
+}
+
+impl Watchpoint {
+    pub fn new(addresses: Range<u16>, kind: WatchKind) -> Self {
+        Self { addresses, kind }
+    }
+
+    /// If the access touches the watched addresses and is of the watched kind
+    pub fn matches(&self, access: &MemoryAccess) -> bool {
+        let (address, len) = match (access, self.kind) {
+            (MemoryAccess::Read { .. }, WatchKind::Write)
+            | (MemoryAccess::Write { .. }, WatchKind::Read) => return false,
+            _ => access.range(),
+        };
+        let start = address as usize;
+        start < self.addresses.end as usize && (self.addresses.start as usize) < start + len
+    }
+}
+
+/// Why [Vm::run] stopped at a breakpoint or watchpoint, see [crate::ExitReason::Stopped]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StopReason {
+    /// `ic` reached the breakpoint, the instruction was not executed yet
+    Breakpoint(u16),
+    /// The last executed instruction made the memory access
+    Watchpoint {
+        watchpoint: Watchpoint,
+        access: MemoryAccess,
+    },
+}
+
+impl Vm {
+    /// Stop [Vm::run] before the instruction at `ic`
+    pub fn add_breakpoint(&mut self, ic: u16) {
+        self.breakpoints.insert(ic, None);
+    }
+
+    /// Stop [Vm::run] before the instruction at `ic` if the condition holds
+    pub fn add_conditional_breakpoint(&mut self, ic: u16, condition: Condition) {
+        self.breakpoints.insert(ic, Some(condition));
+    }
+
+    /// Returns false if there was no breakpoint at `ic`
+    pub fn remove_breakpoint(&mut self, ic: u16) -> bool {
+        self.breakpoints.remove(&ic).is_some()
+    }
+
+    /// Every breakpoint with its condition, sorted by `ic`
+    pub fn breakpoints(&self) -> Vec<(u16, Option<Condition>)> {
+        let mut breakpoints: Vec<_> = self.breakpoints.iter().map(|(&ic, &c)| (ic, c)).collect();
+        breakpoints.sort_by_key(|(ic, _)| *ic);
+        breakpoints
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        self.watchpoints.push(watchpoint);
+    }
+
+    /// Returns false if the watchpoint didn't exist
+    pub fn remove_watchpoint(&mut self, watchpoint: &Watchpoint) -> bool {
+        let len = self.watchpoints.len();
+        self.watchpoints.retain(|watch| watch != watchpoint);
+        self.watchpoints.len() != len
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    /// Breakpoint that stops the instruction at `ic`.
+    /// The breakpoint [Vm::run] last stopped at is skipped so running again continues.
+    pub(crate) fn hit_breakpoint(&self) -> Option<StopReason> {
+        let ic = self.registers[Register::Ic];
+        if self.stopped_at == Some(ic) {
+            return None;
+        }
+        let condition = self.breakpoints.get(&ic)?;
+        match condition {
+            Some(condition) if !condition.holds(&self.registers) => None,
+            _ => Some(StopReason::Breakpoint(ic)),
+        }
+    }
+
+    /// First watchpoint triggered by the memory accesses of the step
+    pub(crate) fn hit_watchpoint(&self, step: &Step) -> Option<StopReason> {
+        step.memory.iter().find_map(|access| {
+            let watchpoint = self.watchpoints.iter().find(|w| w.matches(access))?;
+            Some(StopReason::Watchpoint {
+                watchpoint: watchpoint.clone(),
+                access: access.clone(),
+            })
+        })
+    }
+}
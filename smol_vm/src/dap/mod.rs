@@ -0,0 +1,509 @@
+//! Debug Adapter Protocol server, so editors can debug smol programs.
+//!
+//! Messages are JSON bodies after a `Content-Length` header, see
+//! <https://microsoft.github.io/debug-adapter-protocol/specification>.
+//! Breakpoints and the stack trace use the debug information the assembler
+//! writes next to the object file, see [DebugInfo].
+
+mod json;
+
+use std::io::{self, BufRead, Write};
+
+use smol_file::{DebugInfo, SmolFile};
+
+pub use json::Json;
+
+use crate::{syscall::Sandbox, ExitReason, Register, StopReason, Vm, VmError};
+
+/// `variablesReference` of the registers scope
+const REGISTERS_REFERENCE: i64 = 1;
+/// `variablesReference` of the `---` section variables scope
+const VARIABLES_REFERENCE: i64 = 2;
+/// The VM has a single thread of execution
+const THREAD_ID: i64 = 1;
+
+/// How far `next`, `stepIn` and `stepOut` go
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StepKind {
+    /// Until the next line, entering calls
+    In,
+    /// Until the next line of the same call
+    Over,
+    /// Until the current call returned
+    Out,
+}
+
+/// What happens to the program after the response to a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Resume {
+    /// Report the stop at the first instruction
+    Entry,
+    Run,
+    Step(StepKind),
+}
+
+/// Debug Adapter Protocol server running a single program.
+///
+/// The program runs in a [Sandbox] and its output is forwarded as `output` events.
+/// Execution happens while a request is handled, so `pause` only has an effect
+/// between two requests.
+#[derive(Debug, Default)]
+pub struct DapServer {
+    /// Program being debugged, once launched
+    vm: Option<Vm>,
+    sandbox: Sandbox,
+    debug_info: DebugInfo,
+    /// Sequence number of the last message sent
+    seq: i64,
+    stop_on_entry: bool,
+    /// Messages waiting to be sent after handling a request
+    outgoing: Vec<Json>,
+}
+
+impl DapServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer requests until the input is closed or the client disconnects
+    pub fn serve<R: BufRead, W: Write>(&mut self, input: &mut R, output: &mut W) -> io::Result<()> {
+        while let Some(request) = read_message(input)? {
+            let running = self.handle(&request);
+            for message in std::mem::take(&mut self.outgoing) {
+                write_message(output, &message)?;
+            }
+            output.flush()?;
+            if !running {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle the request and queue the response and the events it caused.
+    /// Returns `false` once the session is over.
+    pub fn handle(&mut self, request: &Json) -> bool {
+        let command = request.get("command").and_then(Json::as_str).unwrap_or("");
+        let args = request.get("arguments").cloned().unwrap_or(Json::Null);
+        // Requests that resume the program reply first and run afterwards,
+        // so the stopped event comes after the response
+        let mut resume = None;
+        let result = match command {
+            "initialize" => Ok(Json::object([
+                ("supportsConfigurationDoneRequest", true.into()),
+                ("supportsTerminateRequest", true.into()),
+            ])),
+            "launch" => self.launch(&args),
+            "setBreakpoints" => self.set_breakpoints(&args),
+            "configurationDone" => {
+                resume = Some(if self.stop_on_entry {
+                    Resume::Entry
+                } else {
+                    Resume::Run
+                });
+                Ok(Json::Null)
+            }
+            "threads" => Ok(Json::object([(
+                "threads",
+                vec![Json::object([
+                    ("id", THREAD_ID.into()),
+                    ("name", "main".into()),
+                ])]
+                .into(),
+            )])),
+            "stackTrace" => self.stack_trace(),
+            "scopes" => Ok(Json::object([(
+                "scopes",
+                vec![
+                    scope("Registers", REGISTERS_REFERENCE),
+                    scope("Variables", VARIABLES_REFERENCE),
+                ]
+                .into(),
+            )])),
+            "variables" => self.variables(&args),
+            "continue" => {
+                resume = Some(Resume::Run);
+                Ok(Json::object([("allThreadsContinued", true.into())]))
+            }
+            "next" => {
+                resume = Some(Resume::Step(StepKind::Over));
+                Ok(Json::Null)
+            }
+            "stepIn" => {
+                resume = Some(Resume::Step(StepKind::In));
+                Ok(Json::Null)
+            }
+            "stepOut" => {
+                resume = Some(Resume::Step(StepKind::Out));
+                Ok(Json::Null)
+            }
+            "pause" => Ok(Json::Null),
+            "disconnect" | "terminate" => Ok(Json::Null),
+            _ => Err(format!("Unsupported request '{command}'")),
+        };
+        let success = result.is_ok();
+        self.respond(request, command, result);
+
+        match command {
+            "launch" if success => self.event("initialized", Json::Null),
+            "pause" => self.stopped("pause", None),
+            "terminate" => self.event("terminated", Json::Null),
+            "disconnect" => return false,
+            _ => {}
+        }
+        match resume {
+            Some(Resume::Entry) => self.stopped("entry", None),
+            Some(Resume::Run) => self.run(),
+            Some(Resume::Step(kind)) => self.step(kind),
+            None => {}
+        }
+        true
+    }
+
+    fn launch(&mut self, args: &Json) -> Result<Json, String> {
+        let program = args
+            .get("program")
+            .and_then(Json::as_str)
+            .ok_or("Missing 'program'")?;
+        let file = SmolFile::try_load(program)?;
+        self.stop_on_entry = args
+            .get("stopOnEntry")
+            .and_then(Json::as_bool)
+            .unwrap_or(false);
+        // Without debug information every instruction is its own line
+        self.debug_info = DebugInfo::load(&DebugInfo::path_for(program)).unwrap_or_default();
+
+        let mut vm = Vm::with_syscalls(Box::new(self.sandbox.clone()));
+        vm.load_program(file)
+            .map_err(|err| format!("{program}: {err}"))?;
+        self.vm = Some(vm);
+        Ok(Json::Null)
+    }
+
+    fn set_breakpoints(&mut self, args: &Json) -> Result<Json, String> {
+        let vm = self.vm.as_mut().ok_or("The program wasn't launched")?;
+        for (ic, _) in vm.breakpoints() {
+            vm.remove_breakpoint(ic);
+        }
+
+        let requested = args
+            .get("breakpoints")
+            .and_then(Json::as_array)
+            .unwrap_or_default();
+        let mut breakpoints = Vec::new();
+        for breakpoint in requested {
+            let line = breakpoint.get("line").and_then(Json::as_i64).unwrap_or(0);
+            let entry = u32::try_from(line)
+                .ok()
+                .and_then(|line| self.debug_info.ic_of(line));
+            breakpoints.push(match entry {
+                Some(entry) => {
+                    vm.add_breakpoint(entry.ic);
+                    Json::object([
+                        ("verified", true.into()),
+                        ("line", (entry.line as i64).into()),
+                    ])
+                }
+                None => Json::object([
+                    ("verified", false.into()),
+                    ("line", line.into()),
+                    ("message", "No instruction on or after this line".into()),
+                ]),
+            });
+        }
+        Ok(Json::object([("breakpoints", breakpoints.into())]))
+    }
+
+    fn stack_trace(&self) -> Result<Json, String> {
+        let vm = self.vm.as_ref().ok_or("The program wasn't launched")?;
+        let ic = vm.registers[Register::Ic];
+        let mut frame = vec![
+            ("id", 0.into()),
+            ("name", format!("ic {ic}").into()),
+            ("line", (self.current_line(ic) as i64).into()),
+            ("column", 1.into()),
+            ("instructionPointerReference", ic.to_string().into()),
+        ];
+        if !self.debug_info.source.is_empty() {
+            frame.push((
+                "source",
+                Json::object([("path", self.debug_info.source.as_str().into())]),
+            ));
+        }
+        let frame = Json::Object(
+            frame
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        );
+        Ok(Json::object([
+            ("stackFrames", vec![frame].into()),
+            ("totalFrames", 1.into()),
+        ]))
+    }
+
+    fn variables(&self, args: &Json) -> Result<Json, String> {
+        let vm = self.vm.as_ref().ok_or("The program wasn't launched")?;
+        let variables = match args.get("variablesReference").and_then(Json::as_i64) {
+            Some(REGISTERS_REFERENCE) => Register::ALL
+                .into_iter()
+                .map(|register| {
+                    let value = vm.registers.get(register);
+                    let value = if register.is_16b() {
+                        format!("{value:#06x} ({value})")
+                    } else {
+                        format!("{value:#04x} ({value})")
+                    };
+                    variable(register.name(), value)
+                })
+                .collect(),
+            Some(VARIABLES_REFERENCE) => self
+                .debug_info
+                .variables
+                .iter()
+                .map(|var| {
+                    let start = var.address as usize;
+                    let end = (start + var.size as usize).min(vm.stack.memory().len());
+                    let bytes = vm.stack.memory()[start.min(end)..end]
+                        .iter()
+                        .map(|byte| format!("{byte:02x}"))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    variable(&var.name, bytes)
+                })
+                .collect(),
+            _ => return Err("Unknown variablesReference".to_string()),
+        };
+        Ok(Json::object([("variables", Json::Array(variables))]))
+    }
+
+    /// Line of the source containing the instruction at `ic`, 0 if unknown
+    fn current_line(&self, ic: u16) -> u32 {
+        if self.debug_info.lines.is_empty() {
+            return ic as u32;
+        }
+        self.debug_info
+            .lines
+            .iter()
+            .filter(|entry| entry.ic <= ic)
+            .max_by_key(|entry| entry.ic)
+            .map_or(0, |entry| entry.line)
+    }
+
+    /// Line starting at `ic`, every instruction starts a line without debug information
+    fn line_at(&self, ic: u16) -> Option<u32> {
+        if self.debug_info.lines.is_empty() {
+            return Some(ic as u32);
+        }
+        self.debug_info.line_of(ic)
+    }
+
+    /// Continue until a breakpoint or the end of the program
+    fn run(&mut self) {
+        let Some(vm) = &mut self.vm else {
+            return;
+        };
+        let result = vm.run();
+        self.forward_output();
+        match result {
+            Ok(ExitReason::EndOfInstructions) => self.exited(0),
+            Ok(ExitReason::Exit(code)) => self.exited(code),
+            Ok(ExitReason::Stopped {
+                reason: StopReason::Breakpoint(_),
+            }) => self.stopped("breakpoint", None),
+            Ok(ExitReason::Stopped {
+                reason: StopReason::Watchpoint { .. },
+            }) => self.stopped("data breakpoint", None),
+            Ok(ExitReason::OutOfFuel | ExitReason::Timeout) => self.stopped("pause", None),
+            Err(err) => self.fault(err),
+        }
+    }
+
+    /// Execute instructions until the step of `kind` is done
+    fn step(&mut self, kind: StepKind) {
+        let result = self.step_until(kind);
+        self.forward_output();
+        match result {
+            Ok(_) if self.vm.as_ref().is_some_and(Vm::is_finished) => {
+                let code = self.vm.as_ref().and_then(Vm::exit_code).unwrap_or(0);
+                self.exited(code);
+            }
+            Ok(reason) => self.stopped(reason, None),
+            Err(err) => self.fault(err),
+        }
+    }
+
+    /// Returns the reason of the stop
+    fn step_until(&mut self, kind: StepKind) -> Result<&'static str, VmError> {
+        let Some(mut vm) = self.vm.take() else {
+            return Ok("step");
+        };
+        let start_line = self.line_at(vm.registers[Register::Ic]);
+        // Calls entered minus calls returned from
+        let mut depth = 0i32;
+        let result = loop {
+            let step = match vm.step() {
+                Ok(Some(step)) => step,
+                Ok(None) => break Ok("step"),
+                Err(err) => break Err(err),
+            };
+            match step.mnemonic {
+                "call" => depth += 1,
+                "ret" | "reti" => depth -= 1,
+                _ => {}
+            }
+            if vm.is_finished() {
+                break Ok("step");
+            }
+            if vm.hit_breakpoint().is_some() {
+                break Ok("breakpoint");
+            }
+            let line = self.line_at(vm.registers[Register::Ic]);
+            let done = match kind {
+                StepKind::In => line.is_some() && (line != start_line || depth != 0),
+                StepKind::Over => depth < 0 || (depth == 0 && line.is_some() && line != start_line),
+                StepKind::Out => depth < 0,
+            };
+            if done {
+                break Ok("step");
+            }
+        };
+        // Continuing must not stop again at the breakpoint the step stopped at
+        vm.stopped_at = Some(vm.registers[Register::Ic]);
+        self.vm = Some(vm);
+        result
+    }
+
+    /// Send what the program wrote since the last stop
+    fn forward_output(&mut self) {
+        for (category, output) in [
+            ("stdout", self.sandbox.take_stdout()),
+            ("stderr", self.sandbox.take_stderr()),
+        ] {
+            if !output.is_empty() {
+                self.event(
+                    "output",
+                    Json::object([
+                        ("category", category.into()),
+                        (
+                            "output",
+                            String::from_utf8_lossy(&output).into_owned().into(),
+                        ),
+                    ]),
+                );
+            }
+        }
+    }
+
+    fn fault(&mut self, err: VmError) {
+        let text = err.to_string();
+        self.event(
+            "output",
+            Json::object([
+                ("category", "stderr".into()),
+                ("output", format!("{text}\n").into()),
+            ]),
+        );
+        self.stopped("exception", Some(text));
+    }
+
+    fn stopped(&mut self, reason: &str, text: Option<String>) {
+        let mut body = vec![
+            ("reason".to_string(), reason.into()),
+            ("threadId".to_string(), THREAD_ID.into()),
+            ("allThreadsStopped".to_string(), true.into()),
+        ];
+        if let Some(text) = text {
+            body.push(("text".to_string(), text.into()));
+        }
+        self.event("stopped", Json::Object(body));
+    }
+
+    fn exited(&mut self, code: u8) {
+        self.event("exited", Json::object([("exitCode", (code as i64).into())]));
+        self.event("terminated", Json::Null);
+    }
+
+    fn respond(&mut self, request: &Json, command: &str, result: Result<Json, String>) {
+        let request_seq = request.get("seq").and_then(Json::as_i64).unwrap_or(0);
+        let mut message = vec![
+            ("type".to_string(), "response".into()),
+            ("request_seq".to_string(), request_seq.into()),
+            ("success".to_string(), result.is_ok().into()),
+            ("command".to_string(), command.into()),
+        ];
+        match result {
+            Ok(Json::Null) => {}
+            Ok(body) => message.push(("body".to_string(), body)),
+            Err(err) => message.push(("message".to_string(), err.into())),
+        }
+        self.send(message);
+    }
+
+    fn event(&mut self, event: &str, body: Json) {
+        let mut message = vec![
+            ("type".to_string(), "event".into()),
+            ("event".to_string(), event.into()),
+        ];
+        if body != Json::Null {
+            message.push(("body".to_string(), body));
+        }
+        self.send(message);
+    }
+
+    fn send(&mut self, mut message: Vec<(String, Json)>) {
+        self.seq += 1;
+        message.insert(0, ("seq".to_string(), self.seq.into()));
+        self.outgoing.push(Json::Object(message));
+    }
+}
+
+fn scope(name: &str, reference: i64) -> Json {
+    Json::object([
+        ("name", name.into()),
+        ("variablesReference", reference.into()),
+        ("expensive", false.into()),
+    ])
+}
+
+fn variable(name: &str, value: String) -> Json {
+    Json::object([
+        ("name", name.into()),
+        ("value", value.into()),
+        ("variablesReference", 0.into()),
+    ])
+}
+
+/// Read the next message, `None` at the end of the input
+pub fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<Json>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing Content-Length"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    let body =
+        String::from_utf8(body).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    Json::parse(&body)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+pub fn write_message<W: Write>(output: &mut W, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())
+}
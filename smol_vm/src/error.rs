@@ -0,0 +1,89 @@
+use std::fmt;
+
+use crate::{registers::Registers, syscall::PolicyViolation};
+
+/// Reason an instruction could not be executed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VmErrorKind {
+    /// The opcode does not decode into an instruction
+    InvalidOpcode,
+    /// `ic` points past the end of the instructions,
+    /// or the next instruction starts past the 64kib `ic` can address
+    InstructionOutOfRange,
+    /// The operands of the instruction are cut off by the end of the instructions
+    TruncatedInstruction,
+    /// A branch, call or return targets outside of the instructions
+    BranchOutOfRange { target: i32 },
+    /// The register encoding is not mapped to a register
+    InvalidRegister(u8),
+    /// The system call id in `r0` is not supported
+    UnknownSyscall(u8),
+    /// The system call broke the [crate::syscall::Policy]
+    SyscallDenied(PolicyViolation),
+    /// The system call doesn't match call `index` of the [crate::syscall::Recording]
+    ReplayMismatch { index: usize },
+    /// Push went past the end of the stack space
+    StackOverflow,
+    /// Pop went past the start of the stack space
+    StackUnderflow,
+    /// Memory access outside of the 64kib memory
+    MemoryOutOfBounds { address: usize, size: usize },
+}
+
+impl From<smol_isa::DecodeError> for VmErrorKind {
+    fn from(err: smol_isa::DecodeError) -> Self {
+        match err {
+            smol_isa::DecodeError::InvalidOpcode => VmErrorKind::InvalidOpcode,
+            smol_isa::DecodeError::Truncated => VmErrorKind::TruncatedInstruction,
+            smol_isa::DecodeError::InvalidRegister(reg) => VmErrorKind::InvalidRegister(reg),
+        }
+    }
+}
+
+impl fmt::Display for VmErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidOpcode => write!(f, "invalid opcode"),
+            Self::InstructionOutOfRange => write!(f, "instruction counter out of range"),
+            Self::TruncatedInstruction => write!(f, "truncated instruction"),
+            Self::BranchOutOfRange { target } => {
+                write!(f, "branch target {target} is outside of the instructions")
+            }
+            Self::InvalidRegister(reg) => write!(f, "invalid register {reg:#06b}"),
+            Self::UnknownSyscall(id) => write!(f, "unknown system call {id}"),
+            Self::SyscallDenied(violation) => write!(f, "system call denied: {violation}"),
+            Self::ReplayMismatch { index } => {
+                write!(f, "system call does not match recorded call {index}")
+            }
+            Self::StackOverflow => write!(f, "stack overflow"),
+            Self::StackUnderflow => write!(f, "stack underflow"),
+            Self::MemoryOutOfBounds { address, size } => {
+                write!(f, "{size} byte memory access at {address} is out of bounds")
+            }
+        }
+    }
+}
+
+/// Fault raised while running the [crate::Vm]
+#[derive(Debug, Clone)]
+pub struct VmError {
+    pub kind: VmErrorKind,
+    /// Instruction counter of the faulting instruction
+    pub ic: u16,
+    /// Opcode at `ic`, if `ic` points into the instructions
+    pub opcode: Option<u8>,
+    /// Snapshot of the registers at the time of the fault
+    pub registers: Registers,
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at ic {}", self.kind, self.ic)?;
+        if let Some(opcode) = self.opcode {
+            write!(f, " (opcode {opcode:#010b})")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for VmError {}
@@ -0,0 +1,371 @@
+use std::io::{self, Read, Write};
+
+use crate::{ExitReason, Register, StopReason, Vm, VmErrorKind, WatchKind, Watchpoint};
+
+/// The memory is mapped after the instructions in the address space seen by GDB,
+/// addresses below are instruction counters
+pub const MEMORY_OFFSET: u32 = 0x1_0000;
+
+/// Register set of the VM in the order of the `g` packet, which is the encoding order
+pub fn target_xml() -> String {
+    let mut xml = String::from(concat!(
+        "<?xml version=\"1.0\"?>\n",
+        "<!DOCTYPE target SYSTEM \"gdb-target.dtd\">\n",
+        "<target version=\"1.0\">\n",
+        "  <feature name=\"org.smol.core\">\n",
+    ));
+    for (regnum, register) in Register::ALL.into_iter().enumerate() {
+        let (bitsize, kind) = match register {
+            Register::Ic => (16, "code_ptr"),
+            Register::Sp => (16, "data_ptr"),
+            _ if register.is_16b() => (16, "uint16"),
+            _ => (8, "uint8"),
+        };
+        xml.push_str(&format!(
+            "    <reg name=\"{register}\" bitsize=\"{bitsize}\" type=\"{kind}\" regnum=\"{regnum}\"/>\n"
+        ));
+    }
+    xml.push_str("  </feature>\n</target>\n");
+    xml
+}
+
+/// GDB remote serial protocol server for a [Vm].
+///
+/// Supports reading and writing the registers and memory, single stepping, continuing,
+/// software breakpoints and watchpoints. Continuing can't be interrupted.
+#[derive(Debug)]
+pub struct GdbStub {
+    pub vm: Vm,
+    /// Acknowledgements were turned off with `QStartNoAckMode`
+    no_ack: bool,
+}
+
+impl GdbStub {
+    pub fn new(vm: Vm) -> Self {
+        Self { vm, no_ack: false }
+    }
+
+    /// Answer packets until the connection is closed or GDB kills the program
+    pub fn serve<C: Read + Write>(&mut self, conn: &mut C) -> io::Result<()> {
+        while let Some(packet) = read_packet(conn)? {
+            let Some(packet) = packet else {
+                conn.write_all(b"-")?;
+                continue;
+            };
+            if !self.no_ack {
+                conn.write_all(b"+")?;
+            }
+            let Some(response) = self.handle(&packet) else {
+                return Ok(());
+            };
+            write_packet(conn, &response)?;
+            if packet == "QStartNoAckMode" {
+                self.no_ack = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Response to the packet, `None` if the session is over
+    fn handle(&mut self, packet: &str) -> Option<String> {
+        let (command, args) = packet.split_at(packet.len().min(1));
+        let response = match command {
+            "?" => "S05".to_string(),
+            "g" => Register::ALL
+                .into_iter()
+                .map(|register| register_hex(&self.vm, register))
+                .collect(),
+            "G" => self.write_registers(args),
+            "p" => match parse_hex(args).and_then(|regnum| Register::ALL.get(regnum as usize)) {
+                Some(&register) => register_hex(&self.vm, register),
+                None => "E01".to_string(),
+            },
+            "P" => self.write_register(args),
+            "m" => self.read_memory(args),
+            "M" => self.write_memory(args),
+            "s" => match self.vm.step() {
+                Ok(_) if self.vm.is_finished() => self.exit_reply(),
+                Ok(_) => "S05".to_string(),
+                Err(err) => fault_reply(&err.kind),
+            },
+            "c" => match self.vm.run() {
+                Ok(reason) => self.stop_reply(reason),
+                Err(err) => fault_reply(&err.kind),
+            },
+            "Z" | "z" => self.breakpoint(command == "Z", args),
+            "k" => return None,
+            // Detaching leaves the VM as it is, there is nothing to resume
+            "D" | "H" => "OK".to_string(),
+            _ => self.query(packet),
+        };
+        Some(response)
+    }
+
+    fn query(&self, packet: &str) -> String {
+        if packet.starts_with("qSupported") {
+            return "PacketSize=4000;qXfer:features:read+;QStartNoAckMode+".to_string();
+        }
+        if let Some(args) = packet.strip_prefix("qXfer:features:read:target.xml:") {
+            let Some((offset, len)) = args.split_once(',') else {
+                return "E01".to_string();
+            };
+            let (Some(offset), Some(len)) = (parse_hex(offset), parse_hex(len)) else {
+                return "E01".to_string();
+            };
+            let xml = target_xml();
+            let start = (offset as usize).min(xml.len());
+            let end = (start + len as usize).min(xml.len());
+            let marker = if end == xml.len() { 'l' } else { 'm' };
+            return format!("{marker}{}", &xml[start..end]);
+        }
+        match packet {
+            "QStartNoAckMode" => "OK",
+            "qAttached" => "1",
+            "qC" => "QC1",
+            "qfThreadInfo" => "m1",
+            "qsThreadInfo" => "l",
+            _ => "",
+        }
+        .to_string()
+    }
+
+    fn stop_reply(&self, reason: ExitReason) -> String {
+        match reason {
+            ExitReason::EndOfInstructions | ExitReason::Exit(_) => self.exit_reply(),
+            ExitReason::Stopped {
+                reason: StopReason::Watchpoint { watchpoint, access },
+            } => {
+                let kind = match watchpoint.kind {
+                    WatchKind::Write => "watch",
+                    WatchKind::Read => "rwatch",
+                    WatchKind::ReadWrite => "awatch",
+                };
+                // Report the first watched byte of the access
+                let (address, _) = access.range();
+                let address = address.max(watchpoint.addresses.start);
+                format!("T05{kind}:{:x};", MEMORY_OFFSET + address as u32)
+            }
+            ExitReason::Stopped { .. } => "S05".to_string(),
+            // The limits are never set by the stub
+            ExitReason::OutOfFuel | ExitReason::Timeout => "S05".to_string(),
+        }
+    }
+
+    fn exit_reply(&self) -> String {
+        format!("W{:02x}", self.vm.exit_code().unwrap_or(0))
+    }
+
+    fn write_registers(&mut self, args: &str) -> String {
+        let Some(bytes) = decode_hex(args) else {
+            return "E01".to_string();
+        };
+        let mut bytes = bytes.into_iter();
+        let mut values = Vec::new();
+        for register in Register::ALL {
+            let low = bytes.next();
+            let high = if register.is_16b() {
+                bytes.next()
+            } else {
+                Some(0)
+            };
+            let (Some(low), Some(high)) = (low, high) else {
+                return "E01".to_string();
+            };
+            values.push((register, u16::from_le_bytes([low, high])));
+        }
+        for (register, value) in values {
+            self.vm.registers.set(register, value);
+        }
+        "OK".to_string()
+    }
+
+    fn write_register(&mut self, args: &str) -> String {
+        let Some((regnum, value)) = args.split_once('=') else {
+            return "E01".to_string();
+        };
+        let register = parse_hex(regnum).and_then(|regnum| Register::ALL.get(regnum as usize));
+        match (register, decode_hex(value).as_deref()) {
+            (Some(&register), Some([low])) if !register.is_16b() => {
+                self.vm.registers.set(register, *low as u16)
+            }
+            (Some(&register), Some([low, high])) if register.is_16b() => self
+                .vm
+                .registers
+                .set(register, u16::from_le_bytes([*low, *high])),
+            _ => return "E01".to_string(),
+        }
+        "OK".to_string()
+    }
+
+    /// Bytes in the GDB address space, see [MEMORY_OFFSET]
+    fn memory(&mut self, address: u32, len: u32) -> Option<&mut [u8]> {
+        let (bytes, start) = match address.checked_sub(MEMORY_OFFSET) {
+            Some(start) => (self.vm.stack.memory_mut(), start),
+            None => (self.vm.instructions.instructions.as_mut_slice(), address),
+        };
+        bytes.get_mut(start as usize..start as usize + len as usize)
+    }
+
+    fn read_memory(&mut self, args: &str) -> String {
+        let Some((address, len)) = parse_address_len(args) else {
+            return "E01".to_string();
+        };
+        match self.memory(address, len) {
+            Some(bytes) => encode_hex(bytes),
+            None => "E14".to_string(),
+        }
+    }
+
+    fn write_memory(&mut self, args: &str) -> String {
+        let Some((range, data)) = args.split_once(':') else {
+            return "E01".to_string();
+        };
+        let (Some((address, len)), Some(data)) = (parse_address_len(range), decode_hex(data))
+        else {
+            return "E01".to_string();
+        };
+        if data.len() != len as usize {
+            return "E01".to_string();
+        }
+        match self.memory(address, len) {
+            Some(bytes) => {
+                bytes.copy_from_slice(&data);
+                "OK".to_string()
+            }
+            None => "E14".to_string(),
+        }
+    }
+
+    /// `Z`/`z` packets, `type,addr,kind`
+    fn breakpoint(&mut self, insert: bool, args: &str) -> String {
+        let mut parts = args.split(',');
+        let (Some(kind), Some(address), Some(len)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return "E01".to_string();
+        };
+        let (Some(address), Some(len)) = (parse_hex(address), parse_hex(len)) else {
+            return "E01".to_string();
+        };
+        let watch = match kind {
+            // Software and hardware breakpoints are the same
+            "0" | "1" => {
+                let Ok(ic) = u16::try_from(address) else {
+                    return "E01".to_string();
+                };
+                if insert {
+                    self.vm.add_breakpoint(ic);
+                } else {
+                    self.vm.remove_breakpoint(ic);
+                }
+                return "OK".to_string();
+            }
+            "2" => WatchKind::Write,
+            "3" => WatchKind::Read,
+            "4" => WatchKind::ReadWrite,
+            _ => return String::new(),
+        };
+        let start = address.checked_sub(MEMORY_OFFSET).map(u16::try_from);
+        let Some(Ok(start)) = start else {
+            return "E01".to_string();
+        };
+        let Some(end) = start.checked_add(len as u16) else {
+            return "E01".to_string();
+        };
+        let watchpoint = Watchpoint::new(start..end, watch);
+        if insert {
+            self.vm.add_watchpoint(watchpoint);
+        } else {
+            self.vm.remove_watchpoint(&watchpoint);
+        }
+        "OK".to_string()
+    }
+}
+
+/// Signal matching the fault, SIGSEGV for memory faults and SIGILL otherwise
+fn fault_reply(kind: &VmErrorKind) -> String {
+    match kind {
+        VmErrorKind::MemoryOutOfBounds { .. }
+        | VmErrorKind::StackOverflow
+        | VmErrorKind::StackUnderflow => "S0b".to_string(),
+        _ => "S04".to_string(),
+    }
+}
+
+/// Little endian value of the register, 8-bit registers are a single byte
+fn register_hex(vm: &Vm, register: Register) -> String {
+    let bytes = vm.registers.get(register).to_le_bytes();
+    if register.is_16b() {
+        encode_hex(&bytes)
+    } else {
+        encode_hex(&bytes[..1])
+    }
+}
+
+fn parse_hex(s: &str) -> Option<u32> {
+    u32::from_str_radix(s, 16).ok()
+}
+
+fn parse_address_len(s: &str) -> Option<(u32, u32)> {
+    let (address, len) = s.split_once(',')?;
+    Some((parse_hex(address)?, parse_hex(len)?))
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|idx| u8::from_str_radix(s.get(idx..idx + 2)?, 16).ok())
+        .collect()
+}
+
+fn read_byte(conn: &mut impl Read) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    match conn.read(&mut byte)? {
+        0 => Ok(None),
+        _ => Ok(Some(byte[0])),
+    }
+}
+
+/// Next `$packet#checksum` from the connection, `Some(None)` if the checksum is wrong
+/// and `None` once the connection is closed. Acknowledgements and interrupts are skipped.
+fn read_packet(conn: &mut impl Read) -> io::Result<Option<Option<String>>> {
+    loop {
+        match read_byte(conn)? {
+            None => return Ok(None),
+            Some(b'$') => break,
+            Some(_) => continue,
+        }
+    }
+
+    let mut data = Vec::new();
+    loop {
+        match read_byte(conn)? {
+            None => return Ok(None),
+            Some(b'#') => break,
+            Some(byte) => data.push(byte),
+        }
+    }
+    let (Some(high), Some(low)) = (read_byte(conn)?, read_byte(conn)?) else {
+        return Ok(None);
+    };
+    let checksum = std::str::from_utf8(&[high, low])
+        .ok()
+        .and_then(|checksum| u8::from_str_radix(checksum, 16).ok());
+    let expected = data.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    if checksum != Some(expected) {
+        return Ok(Some(None));
+    }
+    Ok(Some(String::from_utf8(data).ok()))
+}
+
+fn write_packet(conn: &mut impl Write, data: &str) -> io::Result<()> {
+    let checksum = data.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+    write!(conn, "${data}#{checksum:02x}")?;
+    conn.flush()
+}
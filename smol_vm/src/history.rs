@@ -0,0 +1,77 @@
+use crate::{MemoryAccess, Register, Step, Vm};
+
+impl Vm {
+    /// Keep an undo log of every executed instruction so [Vm::step_back] can revert it.
+    /// Disabling the history drops the log.
+    pub fn set_history(&mut self, enabled: bool) {
+        self.history = enabled.then(Vec::new);
+    }
+
+    /// Executed instructions since the history was enabled, oldest first
+    pub fn history(&self) -> &[Step] {
+        self.history.as_deref().unwrap_or_default()
+    }
+
+    /// Revert the last executed instruction and return it,
+    /// `None` if the history is empty or not enabled.
+    ///
+    /// Only the VM state is reverted, the side effects of system calls on the host are not.
+    pub fn step_back(&mut self) -> Option<Step> {
+        let step = self.history.as_mut()?.pop()?;
+        for write in &step.registers_written {
+            self.registers.set(write.register, write.old);
+        }
+        // Later writes can overlap earlier ones of the same instruction
+        for access in step.memory.iter().rev() {
+            if let MemoryAccess::Write { address, old, .. } = access {
+                let start = *address as usize;
+                self.stack.memory_mut()[start..start + old.len()].copy_from_slice(old);
+            }
+        }
+        self.registers.set(Register::Ic, step.ic);
+        if step.exit_code.is_some() {
+            self.exit_code = None;
+        }
+        Some(step)
+    }
+
+    /// Step back until an instruction matching `stop` was reverted and return it,
+    /// the next [Vm::step] executes it again.
+    /// Returns `None` once the start of the history is reached.
+    pub fn step_back_until(&mut self, mut stop: impl FnMut(&Step) -> bool) -> Option<Step> {
+        while let Some(step) = self.step_back() {
+            if stop(&step) {
+                return Some(step);
+            }
+        }
+        None
+    }
+
+    /// Run backwards until the instruction at one of the breakpoints was reverted
+    pub fn run_back(&mut self, breakpoints: &[u16]) -> Option<Step> {
+        self.step_back_until(|step| breakpoints.contains(&step.ic))
+    }
+
+    /// Go back to right before the last instruction that changed the register
+    pub fn back_to_last_write(&mut self, register: Register) -> Option<Step> {
+        self.step_back_until(|step| {
+            step.registers_written
+                .iter()
+                .any(|write| write.register == register)
+        })
+    }
+
+    /// Go back to right before the last instruction that wrote to the memory address
+    pub fn back_to_last_memory_write(&mut self, address: u16) -> Option<Step> {
+        self.step_back_until(|step| {
+            step.memory.iter().any(|access| match access {
+                MemoryAccess::Write {
+                    address: start,
+                    new,
+                    ..
+                } => (*start as usize..*start as usize + new.len()).contains(&(address as usize)),
+                MemoryAccess::Read { .. } => false,
+            })
+        })
+    }
+}
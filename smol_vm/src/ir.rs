@@ -0,0 +1,206 @@
+use smol_isa::{Family, OperandKind, Operation, MAX_OPERANDS, MAX_SIZE};
+
+use crate::{registers::Register, step::Operand, VmErrorKind};
+
+/// Second operand of an ALU instruction
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum AluSource {
+    Register(Register),
+    Immediate(u8),
+    /// Not, increment and decrement only use the destination
+    Destination,
+}
+
+/// Operand of push and sv, 8 bit registers and immediates stay 8 bit
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum StackValue {
+    /// Register read as 16 bit if `wide` and as 8 bit otherwise
+    Register {
+        register: Register,
+        wide: bool,
+    },
+    Immediate8(u8),
+    Immediate16(u16),
+}
+
+/// Memory address of a LoadStore instruction
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Address {
+    /// `sp` + 8 bit immediate
+    StackOffset(u8),
+    Absolute(u16),
+    /// `sp` + 8 bit register
+    StackRegister(Register),
+    /// 16 bit register
+    Pointer(Register),
+}
+
+/// Instruction with its registers and immediates resolved, ready to execute
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Ir {
+    Alu {
+        operation: Operation,
+        destination: Register,
+        source: AluSource,
+        /// The no-op bit is clear
+        write_back: bool,
+    },
+    Load {
+        register: Register,
+        address: Address,
+        wide: bool,
+    },
+    Store {
+        register: Register,
+        address: Address,
+        wide: bool,
+    },
+    Push(StackValue),
+    Pop {
+        register: Register,
+        wide: bool,
+    },
+    SaveVariable(StackValue),
+    UnsaveVariable,
+    /// Jump and the conditional branches
+    Branch {
+        operation: Operation,
+        offset: i16,
+    },
+    Call {
+        offset: i16,
+    },
+    Return,
+    ReturnInterrupt,
+    Syscall,
+}
+
+/// Decoded instruction and what [crate::Step] reports about it
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Instruction {
+    pub ir: Ir,
+    pub size: u16,
+    pub mnemonic: &'static str,
+    pub operands: [Option<Operand>; MAX_OPERANDS],
+}
+
+impl Instruction {
+    /// Decode the instruction at the start of `bytes`, fails on unmapped registers
+    pub(crate) fn decode(bytes: &[u8]) -> Result<Self, VmErrorKind> {
+        let decoded = smol_isa::decode(bytes)?;
+        let def = decoded.def;
+        let operands = decoded.operands;
+        let register = |index: usize| match operands[index] {
+            Some(Operand::Register(register))
+            | Some(Operand::StackRegister(register))
+            | Some(Operand::Pointer(register)) => register,
+            operand => unreachable!("{operand:?} is not a register"),
+        };
+        let offset = || decoded.values[0] as i16;
+        let stack_value = || match def.operands[0] {
+            OperandKind::Register => StackValue::Register {
+                register: register(0),
+                wide: def.wide,
+            },
+            OperandKind::Immediate8 => StackValue::Immediate8(decoded.values[0] as u8),
+            _ => StackValue::Immediate16(decoded.values[0]),
+        };
+        let address = || match def.operands[1] {
+            OperandKind::StackOffset => Address::StackOffset(decoded.values[1] as u8),
+            OperandKind::Address => Address::Absolute(decoded.values[1]),
+            OperandKind::StackRegister => Address::StackRegister(register(1)),
+            OperandKind::Pointer => Address::Pointer(register(1)),
+            kind => unreachable!("{kind:?} is not an address"),
+        };
+
+        let ir = match (def.family(), def.operation) {
+            (Family::Alu, operation) => Ir::Alu {
+                operation,
+                destination: register(0),
+                source: match def.operands.get(1) {
+                    Some(OperandKind::SourceRegister) => AluSource::Register(register(1)),
+                    Some(_) => AluSource::Immediate(decoded.values[1] as u8),
+                    None => AluSource::Destination,
+                },
+                write_back: decoded.opcode & smol_isa::ALU_NOOP == 0,
+            },
+            (_, Operation::Load) => Ir::Load {
+                register: register(0),
+                address: address(),
+                wide: def.wide,
+            },
+            (_, Operation::Store) => Ir::Store {
+                register: register(0),
+                address: address(),
+                wide: def.wide,
+            },
+            (_, Operation::Push) => Ir::Push(stack_value()),
+            (_, Operation::Pop) => Ir::Pop {
+                register: register(0),
+                wide: def.wide,
+            },
+            (_, Operation::SaveVariable) => Ir::SaveVariable(stack_value()),
+            (_, Operation::UnsaveVariable) => Ir::UnsaveVariable,
+            (_, Operation::Call) => Ir::Call { offset: offset() },
+            (_, Operation::Return) => Ir::Return,
+            (_, Operation::ReturnInterrupt) => Ir::ReturnInterrupt,
+            (_, Operation::Syscall) => Ir::Syscall,
+            (_, operation) => Ir::Branch {
+                operation,
+                offset: offset(),
+            },
+        };
+
+        Ok(Self {
+            ir,
+            size: def.size(),
+            mnemonic: def.mnemonic,
+            operands,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    /// Bytes the instruction was decoded from
+    bytes: [u8; MAX_SIZE],
+    instruction: Instruction,
+}
+
+/// Instructions decoded once by `ic`, see [crate::Vm::set_decode_cache].
+/// Entries are checked against the bytes they were decoded from,
+/// so writing the instructions invalidates them.
+#[derive(Debug, Default)]
+pub(crate) struct DecodeCache {
+    entries: Vec<Option<Entry>>,
+}
+
+impl DecodeCache {
+    /// Instruction at `ic`, decoded if it isn't cached or its bytes changed
+    pub(crate) fn get(&mut self, instructions: &[u8], ic: u16) -> Result<Instruction, VmErrorKind> {
+        if self.entries.len() != instructions.len() {
+            self.entries.clear();
+            self.entries.resize(instructions.len(), None);
+        }
+
+        let ic = ic as usize;
+        let bytes = instructions.get(ic..).unwrap_or_default();
+        if let Some(Some(entry)) = self.entries.get(ic) {
+            let size = entry.instruction.size as usize;
+            if bytes.get(..size) == Some(&entry.bytes[..size]) {
+                return Ok(entry.instruction);
+            }
+        }
+
+        // Errors aren't cached, they stop the program anyway
+        let instruction = Instruction::decode(bytes)?;
+        let size = instruction.size as usize;
+        let mut entry = Entry {
+            bytes: [0; MAX_SIZE],
+            instruction,
+        };
+        entry.bytes[..size].copy_from_slice(&bytes[..size]);
+        self.entries[ic] = Some(entry);
+        Ok(instruction)
+    }
+}
@@ -1,13 +1,50 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::ops::{
     Add, AddAssign, BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Sub,
     SubAssign,
 };
+use std::time::Instant;
 
+use smol_isa::Operation;
+
+mod breakpoint;
+pub mod dap;
+mod error;
+pub mod gdb;
+mod history;
+mod ir;
 mod registers;
+mod snapshot;
+mod step;
 pub mod syscall;
 
-use registers::Registers;
-use syscall::vm_syscall;
+pub use breakpoint::{Comparison, Condition, StopReason, WatchKind, Watchpoint};
+pub use error::{VmError, VmErrorKind};
+use ir::{Address, AluSource, DecodeCache, Instruction, Ir, StackValue};
+pub use registers::{flags, Register, Registers};
+pub use snapshot::Snapshot;
+pub use step::{MemoryAccess, Operand, RegisterWrite, Step};
+use syscall::{Emulated, SyscallHandler, SyscallOutcome};
+
+type VmResult<T> = Result<T, VmErrorKind>;
+
+/// Why [Vm::run] stopped without a fault
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExitReason {
+    /// `ic` reached the end of the instructions
+    EndOfInstructions,
+    /// The program exited through a system call
+    Exit(u8),
+    /// The instruction budget ran out before the instruction at `ic`,
+    /// add more with [Vm::add_fuel] and run again to resume
+    OutOfFuel,
+    /// The deadline passed before the instruction at `ic`,
+    /// move it with [Vm::set_deadline] and run again to resume
+    Timeout,
+    /// A breakpoint or watchpoint triggered, run again to continue
+    Stopped { reason: StopReason },
+}
 
 #[derive(Debug, Clone, Copy)]
 enum Either<L, R> {
@@ -31,6 +68,70 @@ impl RegEither {
             Self::Right(v) => v,
         }
     }
+
+    /// Convert `value` into the same width as `self`
+    fn same_width(self, value: RegEither) -> RegEither {
+        match self {
+            Self::Left(_) => Self::Left(value.as_u8()),
+            Self::Right(_) => Self::Right(value.as_u16()),
+        }
+    }
+
+    /// Highest bit for the width of the value
+    fn sign_bit(self) -> u16 {
+        match self {
+            Self::Left(_) => 0x80,
+            Self::Right(_) => 0x8000,
+        }
+    }
+
+    /// Zero and sign flags of an ALU result
+    fn result_flags(self) -> u16 {
+        let mut fg = 0;
+        if self.as_u16() == 0 {
+            fg |= flags::ZERO;
+        }
+        if self.as_u16() & self.sign_bit() != 0 {
+            fg |= flags::SIGN;
+        }
+        fg
+    }
+
+    /// Wrapping `self + rhs` and the resulting flags
+    fn add_with_flags(self, rhs: RegEither) -> (RegEither, u16) {
+        let result = self + rhs;
+        let (lhs, rhs, res) = (
+            self.as_u16(),
+            self.same_width(rhs).as_u16(),
+            result.as_u16(),
+        );
+        let mut fg = result.result_flags();
+        if res < lhs {
+            fg |= flags::CARRY;
+        }
+        if (lhs ^ res) & (rhs ^ res) & self.sign_bit() != 0 {
+            fg |= flags::OVERFLOW;
+        }
+        (result, fg)
+    }
+
+    /// Wrapping `self - rhs` and the resulting flags, carry is set on borrow
+    fn sub_with_flags(self, rhs: RegEither) -> (RegEither, u16) {
+        let result = self - rhs;
+        let (lhs, rhs, res) = (
+            self.as_u16(),
+            self.same_width(rhs).as_u16(),
+            result.as_u16(),
+        );
+        let mut fg = result.result_flags();
+        if lhs < rhs {
+            fg |= flags::CARRY;
+        }
+        if (lhs ^ rhs) & (lhs ^ res) & self.sign_bit() != 0 {
+            fg |= flags::OVERFLOW;
+        }
+        (result, fg)
+    }
 }
 
 impl From<u8> for RegEither {
@@ -46,10 +147,10 @@ impl From<u16> for RegEither {
 }
 
 macro_rules! either_oper {
-    ($lvalue:expr, $rvalue:expr, $op:tt) => {
+    ($lvalue:expr, $rvalue:expr, $op:ident) => {
         match $lvalue {
-            Either::Left(value) => Either::Left(value $op $rvalue.as_u8()),
-            Either::Right(value) => Either::Right(value $op $rvalue.as_u16()),
+            Either::Left(value) => Either::Left(value.$op($rvalue.as_u8())),
+            Either::Right(value) => Either::Right(value.$op($rvalue.as_u16())),
         }
     };
 }
@@ -58,7 +159,7 @@ impl Add for RegEither {
     type Output = RegEither;
 
     fn add(self, rhs: Self) -> Self::Output {
-        either_oper!(self, rhs, +)
+        either_oper!(self, rhs, wrapping_add)
     }
 }
 
@@ -66,7 +167,7 @@ impl Sub for RegEither {
     type Output = RegEither;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        either_oper!(self, rhs, -)
+        either_oper!(self, rhs, wrapping_sub)
     }
 }
 
@@ -74,7 +175,7 @@ impl BitAnd for RegEither {
     type Output = RegEither;
 
     fn bitand(self, rhs: Self) -> Self::Output {
-        either_oper!(self, rhs, &)
+        either_oper!(self, rhs, bitand)
     }
 }
 
@@ -82,7 +183,7 @@ impl BitOr for RegEither {
     type Output = RegEither;
 
     fn bitor(self, rhs: Self) -> Self::Output {
-        either_oper!(self, rhs, |)
+        either_oper!(self, rhs, bitor)
     }
 }
 
@@ -90,7 +191,7 @@ impl BitXor for RegEither {
     type Output = RegEither;
 
     fn bitxor(self, rhs: Self) -> Self::Output {
-        either_oper!(self, rhs, ^)
+        either_oper!(self, rhs, bitxor)
     }
 }
 
@@ -135,37 +236,6 @@ impl BitAndAssign for RegEither {
     }
 }
 
-#[derive(Debug)]
-enum Register {
-    Ic,
-    Fg,
-    Cr,
-    Sp,
-    Zr,
-    R0,
-    R1,
-    R2,
-    R3,
-    R4,
-    R5,
-    R6,
-    R7,
-    L0,
-    L1,
-}
-
-#[derive(Debug)]
-struct RegisterValue {
-    value: RegEither,
-    register: Register,
-}
-
-impl RegisterValue {
-    fn new(value: RegEither, register: Register) -> Self {
-        Self { value, register }
-    }
-}
-
 #[derive(Debug)]
 #[allow(dead_code)]
 pub struct Stack {
@@ -240,7 +310,7 @@ impl Default for Stack {
 #[derive(Debug, Default)]
 pub struct Instructions {
     /// Linear set of instructions.
-    /// [Registers.ic] points here.
+    /// [Register::Ic] points here.
     pub instructions: Vec<u8>,
 }
 
@@ -254,247 +324,617 @@ impl Instructions {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 #[allow(dead_code)]
 pub struct Vm {
     pub registers: Registers,
     pub stack: Stack,
     pub instructions: Instructions,
+    /// Backend for the `syscall` instruction
+    pub syscalls: Box<dyn SyscallHandler>,
+    /// Exit code once the program exited through a system call
+    exit_code: Option<u8>,
+    /// Instruction that is currently being executed
+    step: Step,
+    /// If `step` describes the executing instruction, [Vm::run] only pays for it
+    /// while the history or watchpoints need it
+    recording: bool,
+    /// Instructions [Vm::run] can still execute, unlimited if `None`
+    fuel: Option<u64>,
+    /// [Vm::run] stops once this passed
+    deadline: Option<Instant>,
+    /// Undo log of the executed instructions if enabled, see [Vm::step_back]
+    history: Option<Vec<Step>>,
+    /// Breakpoints by `ic` with their optional condition
+    breakpoints: HashMap<u16, Option<Condition>>,
+    watchpoints: Vec<Watchpoint>,
+    /// `ic` of the breakpoint [Vm::run] stopped at, it's skipped when running again
+    stopped_at: Option<u16>,
+    /// Decoded instructions by `ic` if enabled, see [Vm::set_decode_cache]
+    decode_cache: Option<DecodeCache>,
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Self::with_syscalls(Box::new(Emulated))
+    }
 }
 
 impl Vm {
-    fn register_val(&self, reg: u8) -> RegisterValue {
-        match reg {
-            0b0000 => RegisterValue::new(self.registers.r0.into(), Register::R0),
-            0b0001 => RegisterValue::new(self.registers.r1.into(), Register::R1),
-            0b0010 => RegisterValue::new(self.registers.r2.into(), Register::R2),
-            0b0011 => RegisterValue::new(self.registers.r3.into(), Register::R3),
-            0b0100 => RegisterValue::new(self.registers.r4.into(), Register::R4),
-            0b0101 => RegisterValue::new(self.registers.r5.into(), Register::R5),
-            0b0110 => RegisterValue::new(self.registers.r6.into(), Register::R6),
-            0b0111 => RegisterValue::new(self.registers.r7.into(), Register::R7),
-            0b1000 => todo!("What register is 0b1000?"),
-            0b1001 => RegisterValue::new(self.registers.l0.into(), Register::L0),
-            0b1010 => RegisterValue::new(self.registers.l1.into(), Register::L1),
-            0b1011 => RegisterValue::new(self.registers.ic.into(), Register::Ic),
-            0b1100 => RegisterValue::new(self.registers.fg.into(), Register::Fg),
-            0b1101 => RegisterValue::new(self.registers.cr.into(), Register::Cr),
-            0b1110 => RegisterValue::new(self.registers.sp.into(), Register::Sp),
-            0b1111 => RegisterValue::new(self.registers.zr.into(), Register::Zr),
-            _ => unreachable!("Tried to access nonexsisting register {reg}"),
-        }
-    }
-
-    fn register_save(&mut self, reg: RegisterValue) {
-        match reg.register {
-            Register::R0 => self.registers.r0 = reg.value.as_u8(),
-            Register::R1 => self.registers.r1 = reg.value.as_u8(),
-            Register::R2 => self.registers.r2 = reg.value.as_u8(),
-            Register::R3 => self.registers.r3 = reg.value.as_u8(),
-            Register::R4 => self.registers.r4 = reg.value.as_u8(),
-            Register::R5 => self.registers.r5 = reg.value.as_u8(),
-            Register::R6 => self.registers.r6 = reg.value.as_u8(),
-            Register::R7 => self.registers.r7 = reg.value.as_u8(),
-            Register::L0 => self.registers.l0 = reg.value.as_u16(),
-            Register::L1 => self.registers.l1 = reg.value.as_u16(),
-            Register::Ic => self.registers.ic = reg.value.as_u16(),
-            Register::Fg => self.registers.fg = reg.value.as_u16(),
-            Register::Cr => self.registers.cr = reg.value.as_u16(),
-            Register::Sp => self.registers.sp = reg.value.as_u16(),
-            Register::Zr => self.registers.zr = reg.value.as_u16(),
-        }
-    }
-
-    fn immediate_instr(&self, ic: u16) -> u8 {
-        self.instructions.instructions[ic as usize]
-    }
-
-    /// Turn instructions from ic and ic+1 into u16
-    fn immediate_instr_16b(&self, ic: u16) -> u16 {
-        // TODO: make this faster with unsafe
-        let instrs = &self.instructions.instructions[ic as usize..];
-        // The archicture is little endian so we need to create u16 from le bytes
-        u16::from_le_bytes([instrs[0], instrs[1]])
-    }
-
-    fn decode_register(&self, regs: u8) -> RegisterValue {
-        let r0 = regs & 0b1111;
-        self.register_val(r0)
-    }
-
-    fn decode_registers(&self, regs: u8) -> (RegisterValue, RegisterValue) {
-        let r0 = regs & 0b1111;
-        let r1 = (regs >> 4) & 0b1111;
-        (self.register_val(r0), self.register_val(r1))
-    }
-
-    fn decode_alu_instr(&mut self, instr: u8) -> u16 {
-        let (used, source_vals) = match instr & 0b100 {
-            0b000 => {
-                let regs = self.instructions.get(self.registers.ic + 1);
-                (2, self.decode_registers(regs))
-            }
-            // TODO: Do something less hacky
-            0b100 if (instr >> 3) & 0b111 == 0b111 => {
-                let regs = self.instructions.get(self.registers.ic + 1);
-                (2, self.decode_registers(regs))
+    pub fn with_syscalls(syscalls: Box<dyn SyscallHandler>) -> Self {
+        Self {
+            registers: Registers::default(),
+            stack: Stack::default(),
+            instructions: Instructions::default(),
+            syscalls,
+            exit_code: None,
+            step: Step::default(),
+            recording: false,
+            fuel: None,
+            deadline: None,
+            history: None,
+            breakpoints: HashMap::new(),
+            watchpoints: Vec::new(),
+            stopped_at: None,
+            decode_cache: Some(DecodeCache::default()),
+        }
+    }
+
+    /// Load the instructions and initialise the variables of the file,
+    /// fails if a variable doesn't fit in the memory
+    pub fn load_program(&mut self, file: smol_file::SmolFile) -> VmResult<()> {
+        self.instructions.instructions = file.instructions;
+        for storage in file.storage.items {
+            let mem = self.stack.memory_mut();
+            if let Some(data) = storage.init_data {
+                let start = storage.offset as usize;
+                let variable = mem.get_mut(start..start + data.len()).ok_or(
+                    VmErrorKind::MemoryOutOfBounds {
+                        address: start,
+                        size: data.len(),
+                    },
+                )?;
+                variable.copy_from_slice(&data);
             }
-            0b100 => {
-                // TODO: Don't hackily ignore the second encoded register
-                let regs = self.instructions.get(self.registers.ic + 1);
-                let value = self.instructions.get(self.registers.ic + 2);
-                let mut regs = self.decode_registers(regs);
-                regs.1.value = value.into();
-                (3, regs)
+        }
+        Ok(())
+    }
+
+    /// Value of the register in its width
+    fn register_val(&self, register: Register) -> RegEither {
+        let value = self.registers[register];
+        if register.is_16b() {
+            value.into()
+        } else {
+            (value as u8).into()
+        }
+    }
+
+    fn register_save(&mut self, register: Register, value: RegEither) {
+        self.registers.set(register, value.as_u16());
+    }
+
+    /// Every operation sets the result flags of [Register::Fg]. The comparison flags
+    /// [flags::LESS] and [flags::GREATER] are only set by equality and kept by the other
+    /// operations, so a loop counter can be updated between a comparison and its branch.
+    /// The result isn't written back into the destination if the no-op bit is set.
+    fn execute_alu(
+        &mut self,
+        operation: Operation,
+        destination: Register,
+        source: AluSource,
+        write_back: bool,
+    ) {
+        let lhs = self.register_val(destination);
+        let rhs: RegEither = match source {
+            AluSource::Register(register) => self.register_val(register),
+            AluSource::Immediate(value) => value.into(),
+            AluSource::Destination => lhs,
+        };
+
+        let (result, fg) = match operation {
+            Operation::Add => lhs.add_with_flags(rhs),
+            Operation::Sub => lhs.sub_with_flags(rhs),
+            Operation::And => (lhs & rhs, (lhs & rhs).result_flags()),
+            Operation::Or => (lhs | rhs, (lhs | rhs).result_flags()),
+            Operation::Xor => (lhs ^ rhs, (lhs ^ rhs).result_flags()),
+            Operation::Not => (!lhs, (!lhs).result_flags()),
+            // Equality, the destination is set to 1 if equal and 0 otherwise
+            Operation::Eq => {
+                let (_, mut fg) = lhs.sub_with_flags(rhs);
+                fg |= match lhs.as_u16().cmp(&lhs.same_width(rhs).as_u16()) {
+                    Ordering::Equal => 0,
+                    Ordering::Less => flags::LESS,
+                    Ordering::Greater => flags::GREATER,
+                };
+                let equal = fg & flags::ZERO != 0;
+                (lhs.same_width((equal as u8).into()), fg)
             }
-            _ => unreachable!(),
+            Operation::Inc => lhs.add_with_flags((1_u8).into()),
+            Operation::Dec => lhs.sub_with_flags((1_u8).into()),
+            operation => unreachable!("{operation:?} is not an ALU operation"),
+        };
+
+        let fg = match operation {
+            Operation::Eq => fg,
+            _ => fg | self.registers[Register::Fg] & (flags::LESS | flags::GREATER),
+        };
+        self.registers.set(Register::Fg, fg);
+
+        if write_back {
+            self.register_save(destination, result);
+        }
+    }
+
+    fn read_memory(&mut self, address: usize, size: usize) -> VmResult<Vec<u8>> {
+        let bytes = self
+            .stack
+            .memory()
+            .get(address..address + size)
+            .ok_or(VmErrorKind::MemoryOutOfBounds { address, size })?
+            .to_vec();
+        if self.recording {
+            self.step.memory.push(MemoryAccess::Read {
+                address: address as u16,
+                bytes: bytes.clone(),
+            });
+        }
+        Ok(bytes)
+    }
+
+    fn write_memory(&mut self, address: usize, bytes: &[u8]) -> VmResult<()> {
+        let size = bytes.len();
+        let memory = self
+            .stack
+            .memory_mut()
+            .get_mut(address..address + size)
+            .ok_or(VmErrorKind::MemoryOutOfBounds { address, size })?;
+        if self.recording {
+            self.step.memory.push(MemoryAccess::Write {
+                address: address as u16,
+                old: memory.to_vec(),
+                new: bytes.to_vec(),
+            });
+        }
+        memory.copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// The stack grows upwards from the start of the stack space,
+    /// `sp` points to the first free byte.
+    fn stack_push(&mut self, value: RegEither) -> VmResult<()> {
+        let sp = self.registers[Register::Sp] as usize;
+        let bytes = match value {
+            Either::Left(value) => value.to_le_bytes().to_vec(),
+            Either::Right(value) => value.to_le_bytes().to_vec(),
         };
+        let end = sp + bytes.len();
+        if end > self.stack.stack().len() {
+            return Err(VmErrorKind::StackOverflow);
+        }
+
+        self.write_memory(sp, &bytes)?;
+        self.registers.set(Register::Sp, end as u16);
+        Ok(())
+    }
 
-        let mut source_vals = source_vals;
-
-        match (instr >> 3) & 0b111 {
-            // Add
-            0b000 => source_vals.0.value += source_vals.1.value,
-            // Subtract
-            0b001 => source_vals.0.value -= source_vals.1.value,
-            // Binary and
-            0b010 => source_vals.0.value &= source_vals.1.value,
-            // Binary or
-            0b011 => source_vals.0.value |= source_vals.1.value,
-            // Binary xor
-            0b100 => source_vals.0.value ^= source_vals.1.value,
-            // Binary not
-            0b101 => source_vals.0.value = !source_vals.0.value,
-            // Equality
-            0b110 => {
-                // TODO: implement this with branching
-                unimplemented!("ALUEquality is not implemented")
+    fn stack_pop(&mut self, is_16b: bool) -> VmResult<RegEither> {
+        let sp = self.registers[Register::Sp] as usize;
+        let size = if is_16b { 2 } else { 1 };
+        // The stack pointer is outside of the stack space while a variable is loaded
+        if sp > self.stack.stack().len() {
+            return Err(VmErrorKind::StackOverflow);
+        }
+        if sp < size {
+            return Err(VmErrorKind::StackUnderflow);
+        }
+
+        let start = sp - size;
+        let bytes = self.read_memory(start, size)?;
+        self.registers.set(Register::Sp, start as u16);
+        Ok(if is_16b {
+            u16::from_le_bytes([bytes[0], bytes[1]]).into()
+        } else {
+            bytes[0].into()
+        })
+    }
+
+    /// Branch offsets are relative to the start of the branch instruction.
+    /// Jumping to the end of the instructions is allowed and ends the program.
+    fn branch_target(&self, offset: i16) -> VmResult<u16> {
+        let target = self.registers[Register::Ic] as i32 + offset as i32;
+        match u16::try_from(target) {
+            Ok(ic) if ic as usize <= self.instructions.size() => Ok(ic),
+            _ => Err(VmErrorKind::BranchOutOfRange { target }),
+        }
+    }
+
+    /// Returns the next instruction counter, which is the branch target
+    /// if the condition holds or the `following` instruction otherwise
+    fn execute_branch(&self, operation: Operation, offset: i16, following: u16) -> VmResult<u16> {
+        let fg = self.registers[Register::Fg];
+        let condition = match operation {
+            Operation::Jump => true,
+            Operation::BranchEqual => fg & flags::ZERO != 0,
+            Operation::BranchNotEqual => fg & flags::ZERO == 0,
+            Operation::BranchGreater => fg & flags::GREATER != 0,
+            Operation::BranchLess => fg & flags::LESS != 0,
+            operation => unreachable!("{operation:?} is not a branch operation"),
+        };
+        if condition {
+            self.branch_target(offset)
+        } else {
+            Ok(following)
+        }
+    }
+
+    fn execute_syscall(&mut self) -> VmResult<()> {
+        if self.recording {
+            self.step.syscall = Some(self.registers[Register::R0] as u8);
+        }
+        // System calls can write anywhere so diff the whole memory
+        let before = self.recording.then(|| {
+            let read = syscall::read_range(&self.registers, &self.stack);
+            (read, self.stack.memory().to_vec())
+        });
+        let outcome = self
+            .syscalls
+            .syscall(&mut self.registers, &mut self.stack)?;
+        if let Some((read, before)) = before {
+            if let Some(range) = read {
+                self.step.memory.push(MemoryAccess::Read {
+                    address: range.start as u16,
+                    bytes: before[range].to_vec(),
+                });
             }
-            0b111 => {
-                // Decode the increment/decrement function
-                source_vals.0.value = match instr & 0b100 {
-                    // Increment
-                    0b000 => source_vals.0.value + (1_u8).into(),
-                    // Decrement
-                    0b100 => source_vals.0.value - (1_u8).into(),
-                    _ => unreachable!("This statement should be literally impossible"),
-                }
+            self.step.diff_memory(&before, self.stack.memory());
+        }
+        if let SyscallOutcome::Exit(code) = outcome {
+            if self.recording {
+                self.step.exit_code = Some(code);
             }
+            self.exit_code = Some(code);
+        }
+        Ok(())
+    }
+
+    /// Returns the call target, `following` is the return address
+    fn execute_call(&mut self, offset: i16, following: u16) -> VmResult<u16> {
+        let target = self.branch_target(offset)?;
+        // Spill the previous return address so calls can be nested
+        self.stack_push(self.registers[Register::Cr].into())?;
+        self.registers.set(Register::Cr, following);
+        Ok(target)
+    }
 
-            // Since we use and (&) we limit ourself to values 0-3
-            _ => unimplemented!("Only Add AluFamily is implemnted"),
-        }
-        self.register_save(source_vals.0);
-
-        used
-    }
-
-    fn decode_branch_instr(&mut self, instr: u8) {
-        match (instr >> 3) & 0b111 {
-            // Relative jump
-            0b000 => unimplemented!("Relative jump is not implemented"),
-            // Branch if equal
-            0b001 => unimplemented!("Branch if equal is not implemented"),
-            // Branch if not equal
-            0b010 => unimplemented!("Branch if not equal is not implemented"),
-            // Branch if greater than
-            0b011 => unimplemented!("Branch if not equal is not implemented"),
-            // Branch if less than
-            0b100 => unimplemented!("Branch if not equal is not implemented"),
-            // Call
-            0b101 => {
-                if instr & 0b111 == 0b111 {
-                    vm_syscall(&mut self.registers, &mut self.stack);
+    /// Returns the return address
+    fn execute_return(&mut self) -> VmResult<u16> {
+        let target = self.registers[Register::Cr];
+        if target as usize > self.instructions.size() {
+            return Err(VmErrorKind::BranchOutOfRange {
+                target: target as i32,
+            });
+        }
+        let cr = self.stack_pop(true)?.as_u16();
+        self.registers.set(Register::Cr, cr);
+        Ok(target)
+    }
+
+    fn stack_value(&self, value: StackValue) -> RegEither {
+        match value {
+            StackValue::Register { register, wide } => {
+                let value = self.registers[register];
+                if wide {
+                    value.into()
                 } else {
-                    unimplemented!("Only systemcall call is implemented");
-                }
-            }
-            // Return from call
-            0b110 => unimplemented!("Return from call is not implemented"),
-            // Return from interrupt
-            0b111 => unimplemented!("Return from interrupt is not implemented"),
-            // Since we use and (&) we limit ourself to values 0-3
-            _ => unimplemented!("Only Add AluFamily is implemnted"),
-        }
-    }
-
-    fn decode_stack_instr(&mut self, instr: u8) -> u16 {
-        let mut used: u16;
-        match (instr >> 4) & 0b11 {
-            0b00 => unimplemented!("Push is not implemented"),
-            0b01 => unimplemented!("Pop is not implemented"),
-            0b10 => {
-                // We always need to save our stack pointer
-                self.stack.save_stack_pointer(self.registers.sp);
-                // Then we can set the pointer into start of the variable space
-                self.registers.sp = u16::MAX / 2;
-                // Set used to two since only 16 immideate uses 3 (self + 1/2)
-                used = 2;
-                match (instr >> 2) & 0b11 {
-                    // 8 bit and 16 register has the same logic
-                    0b00 | 0b01 => {
-                        let reg = self.instructions.get(self.registers.ic + 1);
-                        self.registers.sp += self.decode_register(reg).value.as_u16();
-                    }
-                    // 8 bit immideate
-                    0b10 => {
-                        self.registers.sp += self.immediate_instr(self.registers.ic + 1) as u16;
-                    }
-                    // 16 bit immideate
-                    0b11 => {
-                        self.registers.sp += self.immediate_instr_16b(self.registers.ic + 1);
-                        used = 3;
-                    }
-                    _ => unreachable!(),
+                    (value as u8).into()
                 }
             }
-            0b11 => {
-                self.registers.sp = self.stack.load_stack_pointer();
-                used = 1;
-            }
-            // Since we use and (&) we limit ourself to values 0-3
-            _ => unimplemented!("decoding stack instr logic is broken"),
+            StackValue::Immediate8(value) => value.into(),
+            StackValue::Immediate16(value) => value.into(),
+        }
+    }
+
+    fn execute_pop(&mut self, register: Register, wide: bool) -> VmResult<()> {
+        let value = self.stack_pop(wide)?;
+        self.register_save(register, value);
+        Ok(())
+    }
+
+    fn execute_save_variable(&mut self, value: StackValue) -> VmResult<()> {
+        // 8 bit and 16 register has the same logic
+        let offset = self.stack_value(value).as_u16();
+        // Variables start at the variable space
+        let sp = (u16::MAX / 2)
+            .checked_add(offset)
+            .ok_or(VmErrorKind::MemoryOutOfBounds {
+                address: (u16::MAX / 2) as usize + offset as usize,
+                size: 0,
+            })?;
+        // We always need to save our stack pointer
+        let saved = (u16::MAX - 2) as usize..u16::MAX as usize;
+        let old = self
+            .recording
+            .then(|| self.stack.memory()[saved.clone()].to_vec());
+        self.stack.save_stack_pointer(self.registers[Register::Sp]);
+        if let Some(old) = old {
+            self.step.memory.push(MemoryAccess::Write {
+                address: saved.start as u16,
+                old,
+                new: self.stack.memory()[saved].to_vec(),
+            });
         }
+        self.registers.set(Register::Sp, sp);
+        Ok(())
+    }
+
+    fn execute_unsave_variable(&mut self) -> VmResult<()> {
+        let saved = (u16::MAX - 2) as usize;
+        self.read_memory(saved, 2)?;
+        self.registers
+            .set(Register::Sp, self.stack.load_stack_pointer());
+        Ok(())
+    }
+
+    /// Memory address of a LoadStore instruction
+    fn memory_address(&self, address: Address) -> usize {
+        let sp = self.registers[Register::Sp] as usize;
+        match address {
+            Address::StackOffset(offset) => sp + offset as usize,
+            Address::Absolute(address) => address as usize,
+            Address::StackRegister(register) => sp + self.registers[register] as u8 as usize,
+            Address::Pointer(register) => self.registers[register] as usize,
+        }
+    }
 
-        used
+    /// Words are 16 bit little endian
+    fn execute_load(&mut self, register: Register, address: Address, wide: bool) -> VmResult<()> {
+        let address = self.memory_address(address);
+        let value = if wide {
+            let bytes = self.read_memory(address, 2)?;
+            u16::from_le_bytes([bytes[0], bytes[1]]).into()
+        } else {
+            self.read_memory(address, 1)?[0].into()
+        };
+        self.register_save(register, value);
+        Ok(())
     }
 
-    fn decode_next_instr(&mut self) {
-        let instr = self.instructions.get(self.registers.ic);
+    fn execute_store(&mut self, register: Register, address: Address, wide: bool) -> VmResult<()> {
+        let value = self.registers[register];
+        let address = self.memory_address(address);
+        if wide {
+            self.write_memory(address, &value.to_le_bytes())
+        } else {
+            self.write_memory(address, &[value as u8])
+        }
+    }
 
-        match (instr >> 6) & 0b11 {
-            0b00 => {
-                let used = self.decode_alu_instr(instr);
-                self.registers.ic += used;
+    fn decode_next_instr(&mut self) -> VmResult<()> {
+        let ic = self.registers[Register::Ic];
+        let instruction = match &mut self.decode_cache {
+            Some(cache) => cache.get(&self.instructions.instructions, ic)?,
+            None => {
+                let bytes = self.instructions.instructions.get(ic as usize..);
+                Instruction::decode(bytes.unwrap_or_default())?
             }
-            0b01 => unimplemented!("LoadStore is not implemented"),
-            0b10 => {
-                let used = self.decode_stack_instr(instr);
-                self.registers.ic += used;
+        };
+
+        if self.recording {
+            self.step.mnemonic = instruction.mnemonic;
+            self.step
+                .operands
+                .extend(instruction.operands.into_iter().flatten());
+        }
+
+        // `ic` can't address past 64kib, even if the instructions are longer
+        let following = ic
+            .checked_add(instruction.size)
+            .ok_or(VmErrorKind::InstructionOutOfRange)?;
+        let next_ic = match instruction.ir {
+            Ir::Alu {
+                operation,
+                destination,
+                source,
+                write_back,
+            } => {
+                self.execute_alu(operation, destination, source, write_back);
+                following
             }
-            0b11 => {
-                self.decode_branch_instr(instr);
-                self.registers.ic += 1;
+            Ir::Load {
+                register,
+                address,
+                wide,
+            } => {
+                self.execute_load(register, address, wide)?;
+                following
             }
-            // Since we use and (&) we limit ourself to values 0-3
-            _ => unreachable!(),
+            Ir::Store {
+                register,
+                address,
+                wide,
+            } => {
+                self.execute_store(register, address, wide)?;
+                following
+            }
+            Ir::Push(value) => {
+                self.stack_push(self.stack_value(value))?;
+                following
+            }
+            Ir::Pop { register, wide } => {
+                self.execute_pop(register, wide)?;
+                following
+            }
+            Ir::SaveVariable(value) => {
+                self.execute_save_variable(value)?;
+                following
+            }
+            Ir::UnsaveVariable => {
+                self.execute_unsave_variable()?;
+                following
+            }
+            Ir::Branch { operation, offset } => {
+                self.execute_branch(operation, offset, following)?
+            }
+            Ir::Call { offset } => self.execute_call(offset, following)?,
+            Ir::Return => self.execute_return()?,
+            // There are no interrupts to return from
+            Ir::ReturnInterrupt => return Err(VmErrorKind::InvalidOpcode),
+            Ir::Syscall => {
+                self.execute_syscall()?;
+                following
+            }
+        };
+        self.registers.set(Register::Ic, next_ic);
+
+        Ok(())
+    }
+
+    /// Decode every instruction once and reuse it, on by default.
+    /// Writing the instructions invalidates what was decoded from them.
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.decode_cache = enabled.then(DecodeCache::default);
+    }
+
+    /// Attach the faulting instruction and register state to the error
+    fn error(&self, kind: VmErrorKind) -> VmError {
+        let ic = self.registers[Register::Ic];
+        VmError {
+            kind,
+            ic,
+            opcode: self.instructions.instructions.get(ic as usize).copied(),
+            registers: self.registers.clone(),
         }
     }
 
-    pub fn run(&mut self) {
-        loop {
-            let ic = self.registers.ic;
+    /// Exit code once the program exited through a system call
+    pub fn exit_code(&self) -> Option<u8> {
+        self.exit_code
+    }
 
-            // Break after the last instruction
-            if ic as usize == self.instructions.size() {
-                break;
-            }
+    /// If `ic` reached the end of the instructions or the program exited
+    pub fn is_finished(&self) -> bool {
+        self.exit_code.is_some()
+            || self.registers[Register::Ic] as usize == self.instructions.size()
+    }
 
-            if ic as usize > self.instructions.size() {
-                panic!("Tried to access non-exsisitng instruction {}", ic);
-            }
+    /// Execute exactly one instruction and describe what it did.
+    /// Returns `None` once the program is finished, see [Vm::is_finished].
+    pub fn step(&mut self) -> Result<Option<Step>, VmError> {
+        let ic = self.registers[Register::Ic];
+
+        // Stop after the last instruction
+        if self.is_finished() {
+            return Ok(None);
+        }
+
+        let before = self.registers.clone();
+        let opcode = self.instructions.instructions.get(ic as usize);
+        self.step = Step::new(ic, opcode.copied().unwrap_or_default());
+        self.recording = true;
+        let executed = self.execute();
+        self.recording = false;
+        executed?;
+
+        let mut step = std::mem::take(&mut self.step);
+        step.next_ic = self.registers[Register::Ic];
+        step.diff_registers(&before, &self.registers);
+        if let Some(history) = &mut self.history {
+            history.push(step.clone());
+        }
+        Ok(Some(step))
+    }
+
+    /// Execute the instruction at `ic`, it's only described in `step` while recording
+    fn execute(&mut self) -> Result<(), VmError> {
+        if self.registers[Register::Ic] as usize > self.instructions.size() {
+            return Err(self.error(VmErrorKind::InstructionOutOfRange));
+        }
+        self.decode_next_instr().map_err(|kind| self.error(kind))?;
+        self.stopped_at = None;
+        Ok(())
+    }
 
-            self.decode_next_instr();
+    /// Instructions [Vm::run] can still execute, `None` if unlimited
+    pub fn fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    /// Limit the instructions [Vm::run] can execute, `None` for no limit
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.fuel = fuel;
+    }
+
+    /// Top up the instruction budget, does nothing if it's unlimited
+    pub fn add_fuel(&mut self, fuel: u64) {
+        if let Some(left) = &mut self.fuel {
+            *left = left.saturating_add(fuel);
         }
     }
+
+    /// Stop [Vm::run] once the deadline passed, `None` for no deadline
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    /// Run until the program is finished, the first fault,
+    /// until the fuel or the time ran out or until a breakpoint or watchpoint triggers
+    pub fn run(&mut self) -> Result<ExitReason, VmError> {
+        while !self.is_finished() {
+            if self.fuel == Some(0) {
+                return Ok(ExitReason::OutOfFuel);
+            }
+            if self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                return Ok(ExitReason::Timeout);
+            }
+            if let Some(reason) = self.hit_breakpoint() {
+                self.stopped_at = Some(self.registers[Register::Ic]);
+                return Ok(ExitReason::Stopped { reason });
+            }
+            // Steps are only described for the history and the watchpoints
+            let step = if self.history.is_some() || !self.watchpoints.is_empty() {
+                self.step()?
+            } else {
+                self.execute()?;
+                None
+            };
+            if let Some(fuel) = &mut self.fuel {
+                *fuel -= 1;
+            }
+            if let Some(reason) = step.and_then(|step| self.hit_watchpoint(&step)) {
+                return Ok(ExitReason::Stopped { reason });
+            }
+        }
+        Ok(match self.exit_code {
+            Some(code) => ExitReason::Exit(code),
+            None => ExitReason::EndOfInstructions,
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unusual_byte_groupings)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_only_describes_steps_while_recording() {
+        let mut vm = Vm::default();
+        vm.instructions.instructions = vec![
+            // Stack save variable 8 bit immediate
+            0b10_10_10_00,
+            0,
+            // Jump 8 bit offset
+            0b11_000_0_00,
+            // Back to the save variable
+            -2_i8 as u8,
+        ];
+        vm.set_fuel(Some(1000));
+
+        assert_eq!(vm.run().unwrap(), ExitReason::OutOfFuel);
+        assert!(vm.step.memory.is_empty());
+    }
 }
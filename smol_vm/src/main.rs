@@ -1,24 +1,80 @@
 use std::process::exit;
 
+use smol_vm::syscall::{Recorder, Recording, Replayer, SyscallHandler};
+
+/// Remove `flag` and the value after it from the arguments
+fn take_flag_value(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    let idx = args.iter().position(|arg| arg == flag)?;
+    if idx + 1 >= args.len() {
+        println!("{flag} needs a file as an argument");
+        exit(1);
+    }
+    args.remove(idx);
+    Some(args.remove(idx))
+}
+
 fn main() {
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
+    // Log every system call to stderr
+    let strace = args.iter().any(|arg| arg == "--strace");
+    args.retain(|arg| arg != "--strace");
+    // Save every system call to the file / play them back from it instead of the host
+    let record = take_flag_value(&mut args, "--record");
+    let replay = take_flag_value(&mut args, "--replay");
     if args.len() < 2 {
         println!("Give file as an argument");
         exit(1);
     }
 
     /* let file_contents = fs::read(&args[1]).unwrap(); */
-    let file = smol_file::SmolFile::load(&args[1]);
+    let file = match smol_file::SmolFile::try_load(&args[1]) {
+        Ok(file) => file,
+        Err(err) => {
+            eprintln!("Failed to load {err}");
+            exit(1);
+        }
+    };
 
-    let mut vm = smol_vm::Vm::default();
-    vm.instructions.instructions = file.instructions;
-    for storage in file.storage.items {
-        let mem = vm.stack.memory_mut();
-        if let Some(data) = storage.init_data {
-            let start = storage.offset as usize;
-            let end = start + storage.size as usize;
-            mem[start..end].copy_from_slice(&data);
+    let mut syscalls: Box<dyn SyscallHandler> = match &replay {
+        Some(path) => match Recording::load(path) {
+            Ok(recording) => Box::new(Replayer::new(recording)),
+            Err(err) => {
+                eprintln!("Failed to load {path}: {err}");
+                exit(1);
+            }
+        },
+        None => smol_vm::syscall::host(),
+    };
+    // Keep a handle on the recorder to save the recording after the run
+    let mut recorder = None;
+    if record.is_some() {
+        let handle = Recorder::new(syscalls);
+        syscalls = Box::new(handle.clone());
+        recorder = Some(handle);
+    }
+    if strace {
+        syscalls = Box::new(smol_vm::syscall::Tracer::new(syscalls));
+    }
+    let mut vm = smol_vm::Vm::with_syscalls(syscalls);
+    if let Err(err) = vm.load_program(file) {
+        eprintln!("Failed to load {}: {err}", args[1]);
+        exit(1);
+    }
+    let result = vm.run();
+    if let (Some(path), Some(recorder)) = (&record, &recorder) {
+        if let Err(err) = recorder.recording().save(path) {
+            eprintln!("Failed to save {path}: {err}");
+        }
+    }
+    match result {
+        Ok(smol_vm::ExitReason::EndOfInstructions) => {}
+        Ok(smol_vm::ExitReason::Exit(code)) => exit(code as i32),
+        // No limits are set so the program can't be stopped early
+        Ok(reason) => unreachable!("{reason:?} without limits"),
+        Err(err) => {
+            eprintln!("{err}");
+            eprintln!("{:?}", err.registers);
+            exit(1);
         }
     }
-    vm.run();
 }
@@ -1,37 +1,80 @@
-#[derive(Debug, Default)]
-#[allow(dead_code)]
+use std::fmt;
+use std::ops::Index;
+
+pub use smol_isa::Register;
+
+/// Register file indexed by the 4-bit register encoding, see [Register::encoding].
+///
+/// | Encoding            | Register | Width | Access |
+/// |---------------------|----------|-------|--------|
+/// | `0b0000` - `0b0111` | r0 - r7  | 8     | rw     |
+/// | `0b1000`            | reserved |       |        |
+/// | `0b1001`            | l0       | 16    | rw     |
+/// | `0b1010`            | l1       | 16    | rw     |
+/// | `0b1011`            | ic       | 16    | ro     |
+/// | `0b1100`            | fg       | 16    | ro     |
+/// | `0b1101`            | cr       | 16    | rw     |
+/// | `0b1110`            | sp       | 16    | rw     |
+/// | `0b1111`            | zr       | n     | zero   |
+///
+/// `0b1000` is reserved, instructions that use it fault with
+/// [crate::VmErrorKind::InvalidRegister] and its slot always reads as zero.
+/// Writes to `zr` are discarded so it always reads as zero too.
+/// `ic` and `fg` are read-only to programs, the [crate::Vm] writes them.
+#[derive(Default, Clone, PartialEq, Eq)]
 pub struct Registers {
-    // Special registers
-    /// (16,ro) - Instruction Counter
-    pub ic: u16,
-    /// (16,ro) - Core Flags
-    pub fg: u16,
-    /// (16,rw) - Call Register
-    pub cr: u16,
-    /// (16,rw) - Stack pointer
-    pub sp: u16,
-    /// (16,rw) - Zero register (n-bits wide)
-    pub zr: u16,
-
-    // General purpose registers
-    /// 0th 8-bit general
-    pub r0: u8,
-    /// 1st 8-bit general
-    pub r1: u8,
-    /// 2nd 8-bit general
-    pub r2: u8,
-    /// 3th 8-bit general
-    pub r3: u8,
-    /// 4th 8-bit general
-    pub r4: u8,
-    /// 5th 8-bit general
-    pub r5: u8,
-    /// 6th 8-bit general
-    pub r6: u8,
-    /// 7th 8-bit general
-    pub r7: u8,
-    /// 0th 16-bit general
-    pub l0: u16,
-    /// 1th 16-bit general
-    pub l1: u16,
+    /// 8-bit registers keep their value in the low byte
+    file: [u16; 16],
+}
+
+impl Registers {
+    /// Value of the register, 8-bit registers are zero extended
+    pub fn get(&self, register: Register) -> u16 {
+        self.file[register.encoding() as usize]
+    }
+
+    /// Set the register, 8-bit registers are truncated and `zr` discards the value
+    pub fn set(&mut self, register: Register, value: u16) {
+        self.file[register.encoding() as usize] = match register {
+            Register::Zr => 0,
+            register if register.is_16b() => value,
+            _ => value as u8 as u16,
+        };
+    }
+}
+
+impl Index<Register> for Registers {
+    type Output = u16;
+
+    fn index(&self, register: Register) -> &u16 {
+        &self.file[register.encoding() as usize]
+    }
+}
+
+impl fmt::Debug for Registers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut registers = f.debug_struct("Registers");
+        for register in Register::ALL {
+            registers.field(register.name(), &self[register]);
+        }
+        registers.finish()
+    }
+}
+
+/// Bit layout of the core flags register [Register::Fg]
+pub mod flags {
+    /// The result was zero, or the compared values were equal
+    pub const ZERO: u16 = 0b0000_0001;
+    /// The left value was less than the right value (unsigned),
+    /// only equality sets or clears it
+    pub const LESS: u16 = 0b0000_0010;
+    /// The left value was greater than the right value (unsigned),
+    /// only equality sets or clears it
+    pub const GREATER: u16 = 0b0000_0100;
+    /// Unsigned overflow of an addition or borrow of a subtraction
+    pub const CARRY: u16 = 0b0000_1000;
+    /// Signed overflow of an addition or subtraction
+    pub const OVERFLOW: u16 = 0b0001_0000;
+    /// Highest bit of the result was set
+    pub const SIGN: u16 = 0b0010_0000;
 }
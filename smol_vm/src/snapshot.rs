@@ -0,0 +1,205 @@
+use std::{fs, io};
+
+use crate::{Register, Registers, Vm};
+
+/// Identifies a snapshot file
+const MAGIC: &[u8; 8] = b"SMOLSNAP";
+/// Format version, bumped on every incompatible change
+const VERSION: u8 = 1;
+/// Memory is stored in pages of this size, pages of zeroes are left out
+const PAGE_SIZE: usize = 256;
+
+/// Checkpoint of a [Vm]: the registers, the whole memory and the instructions.
+/// The syscall handler and the run limits are not part of the snapshot.
+///
+/// The file format is little endian like [smol_file]:
+/// - `SMOLSNAP` and the format version byte
+/// - every register in encoding order, 8-bit registers take 1 byte and 16-bit ones 2 bytes
+/// - 1 if the program exited followed by the exit code, 0 otherwise
+/// - number of stored memory pages (u16), then every page as its index (u16)
+///   followed by its 256 bytes, the last page of the memory is 255 bytes
+/// - length of the instructions (u32) followed by the instructions
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub registers: Registers,
+    pub memory: Vec<u8>,
+    pub instructions: Vec<u8>,
+    pub exit_code: Option<u8>,
+}
+
+impl Snapshot {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+
+        write_registers(&mut bytes, &self.registers);
+
+        match self.exit_code {
+            Some(code) => bytes.extend_from_slice(&[1, code]),
+            None => bytes.extend_from_slice(&[0, 0]),
+        }
+
+        let pages: Vec<(usize, &[u8])> = self
+            .memory
+            .chunks(PAGE_SIZE)
+            .enumerate()
+            .filter(|(_, page)| page.iter().any(|&byte| byte != 0))
+            .collect();
+        bytes.extend_from_slice(&(pages.len() as u16).to_le_bytes());
+        for (index, page) in pages {
+            bytes.extend_from_slice(&(index as u16).to_le_bytes());
+            bytes.extend_from_slice(page);
+        }
+
+        bytes.extend_from_slice(&(self.instructions.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.instructions);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let mut reader = Reader { bytes };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(invalid_data("not a smol snapshot"));
+        }
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(invalid_data(&format!(
+                "unsupported snapshot version {version}"
+            )));
+        }
+
+        let registers = reader.registers()?;
+
+        let exited = reader.u8()?;
+        let code = reader.u8()?;
+        let exit_code = (exited != 0).then_some(code);
+
+        let mut memory = vec![0; u16::MAX as usize];
+        for _ in 0..reader.u16()? {
+            let start = reader.u16()? as usize * PAGE_SIZE;
+            let end = (start + PAGE_SIZE).min(memory.len());
+            if start >= end {
+                return Err(invalid_data("memory page out of bounds"));
+            }
+            memory[start..end].copy_from_slice(reader.take(end - start)?);
+        }
+
+        let len = reader.u32()?;
+        let instructions = reader.take(len as usize)?.to_vec();
+        if !reader.bytes.is_empty() {
+            return Err(invalid_data("trailing bytes after the instructions"));
+        }
+
+        Ok(Self {
+            registers,
+            memory,
+            instructions,
+            exit_code,
+        })
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+
+    pub fn load(path: &str) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+}
+
+impl Vm {
+    /// Checkpoint the current state, see [Snapshot]
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            registers: self.registers.clone(),
+            memory: self.stack.memory().to_vec(),
+            instructions: self.instructions.instructions.clone(),
+            exit_code: self.exit_code,
+        }
+    }
+
+    /// Go back to the state of the snapshot, running resumes from its `ic`.
+    /// The history starts over, [Vm::step_back] doesn't go past the snapshot.
+    /// Fails without changing the [Vm] if the snapshot memory isn't the size of the memory.
+    pub fn restore(&mut self, snapshot: Snapshot) -> io::Result<()> {
+        if snapshot.memory.len() != self.stack.memory().len() {
+            return Err(invalid_data(&format!(
+                "snapshot memory is {} bytes instead of {}",
+                snapshot.memory.len(),
+                self.stack.memory().len()
+            )));
+        }
+        self.registers = snapshot.registers;
+        self.stack.memory_mut().copy_from_slice(&snapshot.memory);
+        self.instructions.instructions = snapshot.instructions;
+        self.exit_code = snapshot.exit_code;
+        if let Some(history) = &mut self.history {
+            history.clear();
+        }
+        self.stopped_at = None;
+        Ok(())
+    }
+}
+
+/// Every register in encoding order, 8-bit registers take 1 byte and 16-bit ones 2 bytes
+pub(crate) fn write_registers(bytes: &mut Vec<u8>, registers: &Registers) {
+    for register in Register::ALL {
+        let value = registers.get(register).to_le_bytes();
+        if register.is_16b() {
+            bytes.extend_from_slice(&value);
+        } else {
+            bytes.push(value[0]);
+        }
+    }
+}
+
+/// Cursor over little endian bytes
+pub(crate) struct Reader<'a> {
+    pub(crate) bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if len > self.bytes.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "file is truncated",
+            ));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    pub(crate) fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> io::Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub(crate) fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    /// Registers written by [write_registers]
+    pub(crate) fn registers(&mut self) -> io::Result<Registers> {
+        let mut registers = Registers::default();
+        for register in Register::ALL {
+            let value = if register.is_16b() {
+                self.u16()?
+            } else {
+                self.u8()? as u16
+            };
+            registers.set(register, value);
+        }
+        Ok(registers)
+    }
+}
+
+pub(crate) fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
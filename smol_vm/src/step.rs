@@ -0,0 +1,121 @@
+use std::fmt;
+
+pub use smol_isa::Operand;
+
+use crate::registers::{Register, Registers};
+
+/// Register changed by an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegisterWrite {
+    pub register: Register,
+    pub old: u16,
+    pub new: u16,
+}
+
+/// Memory touched by an instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemoryAccess {
+    Read {
+        address: u16,
+        bytes: Vec<u8>,
+    },
+    Write {
+        address: u16,
+        old: Vec<u8>,
+        new: Vec<u8>,
+    },
+}
+
+impl MemoryAccess {
+    /// First address and the amount of bytes touched
+    pub fn range(&self) -> (u16, usize) {
+        match self {
+            Self::Read { address, bytes } => (*address, bytes.len()),
+            Self::Write { address, new, .. } => (*address, new.len()),
+        }
+    }
+}
+
+/// Description of a single executed instruction, see [crate::Vm::step]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Step {
+    /// Instruction counter the instruction was fetched from
+    pub ic: u16,
+    /// Instruction counter after the instruction
+    pub next_ic: u16,
+    pub opcode: u8,
+    /// Mnemonic of the instruction as written in the assembly
+    pub mnemonic: &'static str,
+    pub operands: Vec<Operand>,
+    /// Registers that changed, not including `ic`
+    pub registers_written: Vec<RegisterWrite>,
+    /// System calls read the buffers of their arguments by the ABI
+    /// and write whatever changed in the memory
+    pub memory: Vec<MemoryAccess>,
+    /// System call id from `r0` if the instruction was a system call
+    pub syscall: Option<u8>,
+    /// Exit code if the system call exited the program
+    pub exit_code: Option<u8>,
+}
+
+impl Step {
+    pub(crate) fn new(ic: u16, opcode: u8) -> Self {
+        Self {
+            ic,
+            opcode,
+            ..Default::default()
+        }
+    }
+
+    /// Record every register that differs between `before` and `after`
+    pub(crate) fn diff_registers(&mut self, before: &Registers, after: &Registers) {
+        self.registers_written = Register::ALL
+            .into_iter()
+            .filter(|reg| *reg != Register::Ic)
+            .filter(|reg| before.get(*reg) != after.get(*reg))
+            .map(|register| RegisterWrite {
+                register,
+                old: before.get(register),
+                new: after.get(register),
+            })
+            .collect();
+    }
+
+    /// Record every changed run of bytes between `before` and `after`
+    pub(crate) fn diff_memory(&mut self, before: &[u8], after: &[u8]) {
+        self.memory.extend(memory_writes(before, after));
+    }
+}
+
+/// Every changed run of bytes between `before` and `after`
+pub(crate) fn memory_writes(before: &[u8], after: &[u8]) -> Vec<MemoryAccess> {
+    let mut writes = Vec::new();
+    let mut idx = 0;
+    while idx < after.len() {
+        if before[idx] == after[idx] {
+            idx += 1;
+            continue;
+        }
+
+        let start = idx;
+        while idx < after.len() && before[idx] != after[idx] {
+            idx += 1;
+        }
+        writes.push(MemoryAccess::Write {
+            address: start as u16,
+            old: before[start..idx].to_vec(),
+            new: after[start..idx].to_vec(),
+        });
+    }
+    writes
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.mnemonic)?;
+        for operand in &self.operands {
+            write!(f, " {operand}")?;
+        }
+        Ok(())
+    }
+}
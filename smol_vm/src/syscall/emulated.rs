@@ -0,0 +1,61 @@
+use std::io::{self, Write};
+
+use crate::{
+    registers::{Register, Registers},
+    Stack, VmErrorKind,
+};
+
+use super::{errno, guest_buffer, SyscallHandler, SyscallOutcome};
+
+/// Portable system calls implemented on top of [std::io].
+/// Uses the same ids and arguments as the x86_64 linux system calls.
+#[derive(Debug, Default)]
+pub struct Emulated;
+
+impl SyscallHandler for Emulated {
+    fn syscall(
+        &mut self,
+        registers: &mut Registers,
+        stack: &mut Stack,
+    ) -> Result<SyscallOutcome, VmErrorKind> {
+        match registers[Register::R0] as u8 {
+            1 => syscall_write(registers, stack),
+            60 => return Ok(SyscallOutcome::Exit(registers[Register::R1] as u8)),
+            id => return Err(VmErrorKind::UnknownSyscall(id)),
+        }
+        Ok(SyscallOutcome::Continue)
+    }
+}
+
+/// Errors are returned as negative values like the linux system calls
+fn syscall_write(registers: &mut Registers, stack: &Stack) {
+    let Some(range) = guest_buffer(
+        registers,
+        stack,
+        registers[Register::R2] as u8,
+        registers[Register::R3] as u8,
+    ) else {
+        registers.set(Register::R0, errno::EFAULT.wrapping_neg().into());
+        return;
+    };
+    let data = &stack.memory()[range];
+
+    // Flush right away since exiting the program doesn't flush
+    let written = match registers[Register::R1] {
+        1 => io::stdout()
+            .lock()
+            .write(data)
+            .and_then(|written| io::stdout().flush().map(|_| written)),
+        2 => io::stderr().write(data),
+        _ => {
+            registers.set(Register::R0, errno::EBADF.wrapping_neg().into());
+            return;
+        }
+    };
+
+    let result = match written {
+        Ok(written) => written as u8,
+        Err(err) => (err.raw_os_error().unwrap_or(5) as u8).wrapping_neg(),
+    };
+    registers.set(Register::R0, result.into());
+}
@@ -1,4 +1,102 @@
+use std::fmt;
+use std::ops::Range;
+
+use crate::{
+    registers::{Register, Registers},
+    Stack, VmErrorKind,
+};
+
+pub mod emulated;
+pub mod policy;
+pub mod record;
+pub mod sandbox;
+pub mod trace;
 #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
 pub mod x86_64_linux;
+
+pub use emulated::Emulated;
+pub use policy::{FdAccess, Policy, PolicyViolation};
+pub use record::{Recorder, Recording, Replayer, SyscallRecord};
+pub use sandbox::Sandbox;
+pub use trace::Tracer;
 #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
-pub use x86_64_linux::vm_syscall;
+pub use x86_64_linux::X86_64Linux;
+
+/// What the VM should do after a system call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyscallOutcome {
+    /// Continue with the next instruction
+    Continue,
+    /// The program exited with the code
+    Exit(u8),
+}
+
+/// Backend for the `syscall` instruction.
+/// The id of the system call is in `r0` and the arguments are in `r1`-`r6`,
+/// the "return" value will be in `r0`.
+pub trait SyscallHandler: fmt::Debug {
+    fn syscall(
+        &mut self,
+        registers: &mut Registers,
+        stack: &mut Stack,
+    ) -> Result<SyscallOutcome, VmErrorKind>;
+}
+
+/// Handler that talks to the host directly where it's supported and is emulated elsewhere
+pub fn host() -> Box<dyn SyscallHandler> {
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    return Box::new(X86_64Linux);
+    #[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+    return Box::new(Emulated);
+}
+
+/// Linux error numbers, system calls return them negated in `r0`
+pub mod errno {
+    /// Bad file descriptor
+    pub const EBADF: u8 = 9;
+    /// Bad address
+    pub const EFAULT: u8 = 14;
+    /// Largest linux error number
+    pub const MAX: u8 = 133;
+
+    /// If the 8-bit result in `r0` is a negated error number,
+    /// results from `-MAX` up can't be told apart from errors
+    pub fn is_error(result: u8) -> bool {
+        result > u8::MAX - MAX
+    }
+}
+
+/// Memory the system call in `r0` reads by the ABI, the buffer of write and the path of open
+pub(crate) fn read_range(registers: &Registers, stack: &Stack) -> Option<Range<usize>> {
+    let range = match registers[Register::R0] as u8 {
+        1 => guest_buffer(
+            registers,
+            stack,
+            registers[Register::R2] as u8,
+            registers[Register::R3] as u8,
+        ),
+        2 => guest_c_str(registers, stack, registers[Register::R1] as u8),
+        _ => None,
+    };
+    range.filter(|range| !range.is_empty())
+}
+
+/// Memory range of the `len` byte buffer at `sp + offset`,
+/// `None` if the buffer doesn't fit into the memory
+fn guest_buffer(registers: &Registers, stack: &Stack, offset: u8, len: u8) -> Option<Range<usize>> {
+    let start = registers[Register::Sp] as usize + offset as usize;
+    let end = start + len as usize;
+    (end <= stack.memory().len()).then_some(start..end)
+}
+
+/// Memory range of the NUL terminated string at `sp + offset` including the NUL,
+/// `None` if the string isn't terminated before the end of the memory
+fn guest_c_str(registers: &Registers, stack: &Stack, offset: u8) -> Option<Range<usize>> {
+    let start = registers[Register::Sp] as usize + offset as usize;
+    let len = stack
+        .memory()
+        .get(start..)?
+        .iter()
+        .position(|&byte| byte == 0)?;
+    Some(start..start + len + 1)
+}
@@ -0,0 +1,190 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use crate::{
+    registers::{Register, Registers},
+    Stack, VmErrorKind,
+};
+
+use super::{errno, SyscallHandler, SyscallOutcome};
+
+/// Access a program has to a file descriptor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FdAccess {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl FdAccess {
+    fn allows(self, access: FdAccess) -> bool {
+        self == FdAccess::ReadWrite || self == access
+    }
+
+    /// Access requested by the flags of open
+    fn from_open_flags(flags: u16) -> Self {
+        match flags & 0b11 {
+            0 => FdAccess::Read,
+            1 => FdAccess::Write,
+            _ => FdAccess::ReadWrite,
+        }
+    }
+}
+
+impl fmt::Display for FdAccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read => write!(f, "read"),
+            Self::Write => write!(f, "write"),
+            Self::ReadWrite => write!(f, "read/write"),
+        }
+    }
+}
+
+/// Rule of a [Policy] broken by a program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyViolation {
+    /// The system call id is not allowed
+    Syscall(u8),
+    /// The file descriptor is not allowed for the access
+    FileDescriptor { fd: u8, access: FdAccess },
+    /// The file descriptor wasn't opened by the program, so it can't be closed or seeked
+    UnknownFileDescriptor(u8),
+    /// The write would go past the limit of written bytes
+    WriteLimit { limit: usize },
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syscall(id) => write!(f, "system call {id} is not allowed"),
+            Self::FileDescriptor { fd, access } => {
+                write!(f, "{access} access to fd {fd} is not allowed")
+            }
+            Self::UnknownFileDescriptor(fd) => write!(f, "fd {fd} was not opened by the program"),
+            Self::WriteLimit { limit } => write!(f, "more than {limit} bytes written"),
+        }
+    }
+}
+
+/// Restricts the system calls of an untrusted program before they reach the inner handler.
+///
+/// Everything is denied until allowed, fds returned by open get the access of its flags
+/// and are forgotten on close. Only those fds can be closed or seeked, the fds allowed
+/// up front stay open. Exit never reaches the inner handler, it only ends the program.
+#[derive(Debug)]
+pub struct Policy {
+    inner: Box<dyn SyscallHandler>,
+    allowed: HashSet<u8>,
+    fds: HashMap<u8, FdAccess>,
+    /// fds returned by open
+    opened: HashSet<u8>,
+    max_bytes_written: Option<usize>,
+    bytes_written: usize,
+}
+
+impl Policy {
+    pub fn new(inner: Box<dyn SyscallHandler>) -> Self {
+        Self {
+            inner,
+            allowed: HashSet::new(),
+            fds: HashMap::new(),
+            opened: HashSet::new(),
+            max_bytes_written: None,
+            bytes_written: 0,
+        }
+    }
+
+    /// Allow the system call id
+    pub fn allow(mut self, id: u8) -> Self {
+        self.allowed.insert(id);
+        self
+    }
+
+    /// Allow the access to the file descriptor
+    pub fn allow_fd(mut self, fd: u8, access: FdAccess) -> Self {
+        self.fds.insert(fd, access);
+        self
+    }
+
+    /// Limit the total amount of bytes the program can write
+    pub fn max_bytes_written(mut self, limit: usize) -> Self {
+        self.max_bytes_written = Some(limit);
+        self
+    }
+
+    /// Bytes written by the allowed writes so far
+    pub fn bytes_written(&self) -> usize {
+        self.bytes_written
+    }
+
+    fn check_fd(&self, fd: u8, access: FdAccess) -> Result<(), VmErrorKind> {
+        match self.fds.get(&fd) {
+            Some(allowed) if allowed.allows(access) => Ok(()),
+            _ => Err(VmErrorKind::SyscallDenied(
+                PolicyViolation::FileDescriptor { fd, access },
+            )),
+        }
+    }
+
+    fn check_opened_fd(&self, fd: u8) -> Result<(), VmErrorKind> {
+        if !self.opened.contains(&fd) {
+            return Err(VmErrorKind::SyscallDenied(
+                PolicyViolation::UnknownFileDescriptor(fd),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl SyscallHandler for Policy {
+    fn syscall(
+        &mut self,
+        registers: &mut Registers,
+        stack: &mut Stack,
+    ) -> Result<SyscallOutcome, VmErrorKind> {
+        let id = registers[Register::R0] as u8;
+        if !self.allowed.contains(&id) {
+            return Err(VmErrorKind::SyscallDenied(PolicyViolation::Syscall(id)));
+        }
+
+        match id {
+            0 => self.check_fd(registers[Register::R1] as u8, FdAccess::Read)?,
+            1 => {
+                self.check_fd(registers[Register::R1] as u8, FdAccess::Write)?;
+                let written = self.bytes_written + registers[Register::R3] as usize;
+                if let Some(limit) = self.max_bytes_written.filter(|&limit| written > limit) {
+                    return Err(VmErrorKind::SyscallDenied(PolicyViolation::WriteLimit {
+                        limit,
+                    }));
+                }
+            }
+            3 | 8 => self.check_opened_fd(registers[Register::R1] as u8)?,
+            60 => return Ok(SyscallOutcome::Exit(registers[Register::R1] as u8)),
+            _ => {}
+        }
+
+        let (fd, flags) = (registers[Register::R1] as u8, registers[Register::L0]);
+        let requested = registers[Register::R3] as u8;
+        let outcome = self.inner.syscall(registers, stack)?;
+        let result = registers[Register::R0] as u8;
+        match id {
+            // Writes never return more than the requested length, so larger results are
+            // errors. Errors that fit the length are charged as well, to stay under the limit
+            1 if result <= requested => self.bytes_written += result as usize,
+            // fds that look like errors stay denied
+            2 if !errno::is_error(result) => {
+                self.fds.insert(result, FdAccess::from_open_flags(flags));
+                self.opened.insert(result);
+            }
+            3 if result == 0 => {
+                self.fds.remove(&fd);
+                self.opened.remove(&fd);
+            }
+            _ => {}
+        }
+        Ok(outcome)
+    }
+}
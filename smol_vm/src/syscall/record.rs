@@ -0,0 +1,222 @@
+use std::{cell::RefCell, fs, io, rc::Rc};
+
+use crate::{
+    registers::{Register, Registers},
+    snapshot::{invalid_data, write_registers, Reader},
+    step::memory_writes,
+    MemoryAccess, Stack, VmErrorKind,
+};
+
+use super::{SyscallHandler, SyscallOutcome};
+
+/// Identifies a recording file
+const MAGIC: &[u8; 7] = b"SMOLREC";
+/// Format version, bumped on every incompatible change
+const VERSION: u8 = 1;
+
+/// One system call with everything needed to play it back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyscallRecord {
+    /// Registers when the program made the call
+    pub before: Registers,
+    /// Registers once the call returned
+    pub after: Registers,
+    /// Bytes the call wrote into the memory as `(address, bytes)`
+    pub writes: Vec<(u16, Vec<u8>)>,
+    /// Exit code if the call exited the program
+    pub exit_code: Option<u8>,
+}
+
+/// Every system call of a run, in order.
+///
+/// The file format is little endian like [crate::Snapshot]:
+/// - `SMOLREC` and the format version byte
+/// - number of calls (u32), then for every call the registers before and after it,
+///   1 and the exit code if it exited or 0 and 0 otherwise,
+///   the number of memory writes (u16) and every write as its address (u16),
+///   its length (u16) and the bytes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recording {
+    pub syscalls: Vec<SyscallRecord>,
+}
+
+impl Recording {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&(self.syscalls.len() as u32).to_le_bytes());
+        for record in &self.syscalls {
+            write_registers(&mut bytes, &record.before);
+            write_registers(&mut bytes, &record.after);
+            match record.exit_code {
+                Some(code) => bytes.extend_from_slice(&[1, code]),
+                None => bytes.extend_from_slice(&[0, 0]),
+            }
+            bytes.extend_from_slice(&(record.writes.len() as u16).to_le_bytes());
+            for (address, data) in &record.writes {
+                bytes.extend_from_slice(&address.to_le_bytes());
+                bytes.extend_from_slice(&(data.len() as u16).to_le_bytes());
+                bytes.extend_from_slice(data);
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let mut reader = Reader { bytes };
+        if reader.take(MAGIC.len())? != MAGIC {
+            return Err(invalid_data("not a smol recording"));
+        }
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(invalid_data(&format!(
+                "unsupported recording version {version}"
+            )));
+        }
+
+        let mut syscalls = Vec::new();
+        for _ in 0..reader.u32()? {
+            let before = reader.registers()?;
+            let after = reader.registers()?;
+            let exited = reader.u8()?;
+            let code = reader.u8()?;
+            let mut writes = Vec::new();
+            for _ in 0..reader.u16()? {
+                let address = reader.u16()?;
+                let len = reader.u16()?;
+                if address as usize + len as usize > u16::MAX as usize {
+                    return Err(invalid_data("memory write out of bounds"));
+                }
+                writes.push((address, reader.take(len as usize)?.to_vec()));
+            }
+            syscalls.push(SyscallRecord {
+                before,
+                after,
+                writes,
+                exit_code: (exited != 0).then_some(code),
+            });
+        }
+        if !reader.bytes.is_empty() {
+            return Err(invalid_data("trailing bytes after the system calls"));
+        }
+
+        Ok(Self { syscalls })
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+
+    pub fn load(path: &str) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+}
+
+/// Passes system calls to the inner handler and records their results.
+///
+/// Clones share the same [Recording] so a clone can be kept to save it after the run.
+/// Exit never reaches the inner handler so the recording can still be saved,
+/// calls failing in the inner handler are not recorded.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    inner: Rc<RefCell<Box<dyn SyscallHandler>>>,
+    recording: Rc<RefCell<Recording>>,
+}
+
+impl Recorder {
+    pub fn new(inner: Box<dyn SyscallHandler>) -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(inner)),
+            recording: Rc::default(),
+        }
+    }
+
+    /// System calls recorded so far
+    pub fn recording(&self) -> Recording {
+        self.recording.borrow().clone()
+    }
+}
+
+impl SyscallHandler for Recorder {
+    fn syscall(
+        &mut self,
+        registers: &mut Registers,
+        stack: &mut Stack,
+    ) -> Result<SyscallOutcome, VmErrorKind> {
+        let before = registers.clone();
+        let memory = stack.memory().to_vec();
+        let outcome = match registers[Register::R0] as u8 {
+            60 => SyscallOutcome::Exit(registers[Register::R1] as u8),
+            _ => self.inner.borrow_mut().syscall(registers, stack)?,
+        };
+
+        let writes = memory_writes(&memory, stack.memory())
+            .into_iter()
+            .filter_map(|access| match access {
+                MemoryAccess::Write { address, new, .. } => Some((address, new)),
+                MemoryAccess::Read { .. } => None,
+            })
+            .collect();
+        let exit_code = match outcome {
+            SyscallOutcome::Exit(code) => Some(code),
+            SyscallOutcome::Continue => None,
+        };
+        self.recording.borrow_mut().syscalls.push(SyscallRecord {
+            before,
+            after: registers.clone(),
+            writes,
+            exit_code,
+        });
+        Ok(outcome)
+    }
+}
+
+/// Plays back a [Recording] without touching the host.
+/// Every call has to be made with the same registers as in the recording,
+/// otherwise the run fails with [VmErrorKind::ReplayMismatch].
+#[derive(Debug)]
+pub struct Replayer {
+    recording: Recording,
+    next: usize,
+}
+
+impl Replayer {
+    pub fn new(recording: Recording) -> Self {
+        Self { recording, next: 0 }
+    }
+
+    /// If every recorded system call was played back
+    pub fn is_done(&self) -> bool {
+        self.next == self.recording.syscalls.len()
+    }
+}
+
+impl SyscallHandler for Replayer {
+    fn syscall(
+        &mut self,
+        registers: &mut Registers,
+        stack: &mut Stack,
+    ) -> Result<SyscallOutcome, VmErrorKind> {
+        let index = self.next;
+        let record = self
+            .recording
+            .syscalls
+            .get(index)
+            .ok_or(VmErrorKind::ReplayMismatch { index })?;
+        if *registers != record.before {
+            return Err(VmErrorKind::ReplayMismatch { index });
+        }
+
+        self.next += 1;
+        *registers = record.after.clone();
+        for (address, data) in &record.writes {
+            let start = *address as usize;
+            stack.memory_mut()[start..start + data.len()].copy_from_slice(data);
+        }
+        Ok(match record.exit_code {
+            Some(code) => SyscallOutcome::Exit(code),
+            None => SyscallOutcome::Continue,
+        })
+    }
+}
@@ -0,0 +1,132 @@
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+use crate::{
+    registers::{Register, Registers},
+    Stack, VmErrorKind,
+};
+
+use super::{errno, guest_buffer, SyscallHandler, SyscallOutcome};
+
+#[derive(Debug, Default)]
+struct SandboxState {
+    stdin: VecDeque<u8>,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    exit_code: Option<u8>,
+}
+
+/// In-memory system calls that never touch the host.
+/// Output to fd 1 and 2 is captured and fd 0 reads from the scripted stdin.
+///
+/// Clones share the same state so a clone can be kept to inspect
+/// the output after handing the sandbox to the [crate::Vm].
+#[derive(Debug, Clone, Default)]
+pub struct Sandbox {
+    state: Rc<RefCell<SandboxState>>,
+}
+
+impl Sandbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_stdin(stdin: &[u8]) -> Self {
+        let sandbox = Self::default();
+        sandbox.push_stdin(stdin);
+        sandbox
+    }
+
+    /// Queue more bytes for the program to read from stdin
+    pub fn push_stdin(&self, stdin: &[u8]) {
+        self.state.borrow_mut().stdin.extend(stdin);
+    }
+
+    /// Everything written to fd 1
+    pub fn stdout(&self) -> Vec<u8> {
+        self.state.borrow().stdout.clone()
+    }
+
+    /// Everything written to fd 2
+    pub fn stderr(&self) -> Vec<u8> {
+        self.state.borrow().stderr.clone()
+    }
+
+    /// Output written to fd 1 since the last call, for forwarding it as it comes
+    pub fn take_stdout(&self) -> Vec<u8> {
+        std::mem::take(&mut self.state.borrow_mut().stdout)
+    }
+
+    /// Output written to fd 2 since the last call
+    pub fn take_stderr(&self) -> Vec<u8> {
+        std::mem::take(&mut self.state.borrow_mut().stderr)
+    }
+
+    /// Exit code of the exit system call, if the program called it
+    pub fn exit_code(&self) -> Option<u8> {
+        self.state.borrow().exit_code
+    }
+}
+
+impl SyscallHandler for Sandbox {
+    fn syscall(
+        &mut self,
+        registers: &mut Registers,
+        stack: &mut Stack,
+    ) -> Result<SyscallOutcome, VmErrorKind> {
+        let mut state = self.state.borrow_mut();
+        let result = match registers[Register::R0] as u8 {
+            0 => state.read(registers, stack),
+            1 => state.write(registers, stack),
+            60 => {
+                let code = registers[Register::R1] as u8;
+                state.exit_code = Some(code);
+                return Ok(SyscallOutcome::Exit(code));
+            }
+            id => return Err(VmErrorKind::UnknownSyscall(id)),
+        };
+        registers.set(Register::R0, result.into());
+        Ok(SyscallOutcome::Continue)
+    }
+}
+
+impl SandboxState {
+    fn read(&mut self, registers: &Registers, stack: &mut Stack) -> u8 {
+        if registers[Register::R1] != 0 {
+            return errno::EBADF.wrapping_neg();
+        }
+        let Some(range) = guest_buffer(
+            registers,
+            stack,
+            registers[Register::R2] as u8,
+            registers[Register::R3] as u8,
+        ) else {
+            return errno::EFAULT.wrapping_neg();
+        };
+
+        let count = range.len().min(self.stdin.len());
+        let buffer = &mut stack.memory_mut()[range];
+        for (dst, src) in buffer.iter_mut().zip(self.stdin.drain(..count)) {
+            *dst = src;
+        }
+        count as u8
+    }
+
+    fn write(&mut self, registers: &Registers, stack: &Stack) -> u8 {
+        let Some(range) = guest_buffer(
+            registers,
+            stack,
+            registers[Register::R2] as u8,
+            registers[Register::R3] as u8,
+        ) else {
+            return errno::EFAULT.wrapping_neg();
+        };
+
+        let data = &stack.memory()[range];
+        match registers[Register::R1] {
+            1 => self.stdout.extend_from_slice(data),
+            2 => self.stderr.extend_from_slice(data),
+            _ => return errno::EBADF.wrapping_neg(),
+        }
+        data.len() as u8
+    }
+}
@@ -0,0 +1,149 @@
+use std::{fmt, io::Write};
+
+use crate::{
+    registers::{Register, Registers},
+    Stack, VmErrorKind,
+};
+
+use super::{errno, guest_buffer, guest_c_str, SyscallHandler, SyscallOutcome};
+
+/// Buffers longer than this are cut off in the log
+const MAX_LOGGED_BYTES: usize = 32;
+
+/// Logs every system call in a strace like format before passing it to the inner handler,
+/// e.g. `write(1, "hi\n", 3) = 3`
+pub struct Tracer {
+    inner: Box<dyn SyscallHandler>,
+    out: Box<dyn Write>,
+}
+
+impl Tracer {
+    /// Tracer that logs to stderr
+    pub fn new(inner: Box<dyn SyscallHandler>) -> Self {
+        Self::with_writer(inner, Box::new(std::io::stderr()))
+    }
+
+    pub fn with_writer(inner: Box<dyn SyscallHandler>, out: Box<dyn Write>) -> Self {
+        Self { inner, out }
+    }
+}
+
+impl fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tracer")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SyscallHandler for Tracer {
+    fn syscall(
+        &mut self,
+        registers: &mut Registers,
+        stack: &mut Stack,
+    ) -> Result<SyscallOutcome, VmErrorKind> {
+        let id = registers[Register::R0] as u8;
+        let r = registers.clone();
+        // Read buffers are only filled in by the call
+        let call = match id {
+            0 => None,
+            _ => Some(format_call(&r, stack)),
+        };
+
+        let outcome = self.inner.syscall(registers, stack);
+        let result = registers[Register::R0] as u8;
+        let error = error_number(id, r[Register::R3] as u8, result);
+        let call = call.unwrap_or_else(|| {
+            // Only the bytes that were read, none on error
+            let len = if error.is_some() { 0 } else { result };
+            format!(
+                "read({}, {}, {})",
+                r[Register::R1],
+                buffer(&r, stack, r[Register::R2] as u8, len),
+                r[Register::R3]
+            )
+        });
+
+        let _ = match &outcome {
+            Ok(SyscallOutcome::Exit(_)) => writeln!(self.out, "{call} = ?"),
+            Ok(SyscallOutcome::Continue) => match error {
+                Some(errno) => writeln!(self.out, "{call} = {result} (-{errno})"),
+                None => writeln!(self.out, "{call} = {result}"),
+            },
+            Err(err) => writeln!(self.out, "{call} = ? ({err})"),
+        };
+        outcome
+    }
+}
+
+/// Error number if the 8-bit `result` of the system call is an error.
+/// Reads and writes return at most the `requested` length,
+/// so only the error numbers above it are errors.
+fn error_number(id: u8, requested: u8, result: u8) -> Option<u8> {
+    let failed = match id {
+        0 | 1 => result > requested && errno::is_error(result),
+        // getpid can't fail
+        39 => false,
+        _ => errno::is_error(result),
+    };
+    failed.then(|| result.wrapping_neg())
+}
+
+/// Call with the arguments decoded for the known system calls
+fn format_call(r: &Registers, stack: &Stack) -> String {
+    match r[Register::R0] {
+        1 => format!(
+            "write({}, {}, {})",
+            r[Register::R1],
+            buffer(r, stack, r[Register::R2] as u8, r[Register::R3] as u8),
+            r[Register::R3]
+        ),
+        2 => {
+            let path = match guest_c_str(r, stack, r[Register::R1] as u8) {
+                Some(range) => quoted(&stack.memory()[range.start..range.end - 1]),
+                None => format!("sp+{}", r[Register::R1]),
+            };
+            format!(
+                "open({path}, {:#o}, {:#o})",
+                r[Register::L0],
+                r[Register::L1]
+            )
+        }
+        3 => format!("close({})", r[Register::R1]),
+        8 => format!(
+            "lseek({}, {}, {})",
+            r[Register::R1],
+            r[Register::L0] as i16,
+            r[Register::R3]
+        ),
+        39 => "getpid()".to_string(),
+        60 => format!("exit({})", r[Register::R1]),
+        201 => match r[Register::R1] {
+            0 => "time(NULL)".to_string(),
+            offset => format!("time(sp+{offset})"),
+        },
+        id => format!(
+            "syscall_{id}({}, {}, {}, {}, {}, {})",
+            r[Register::R1],
+            r[Register::R2],
+            r[Register::R3],
+            r[Register::R4],
+            r[Register::R5],
+            r[Register::R6]
+        ),
+    }
+}
+
+/// Contents of the buffer at `sp + offset`, or only its address if it's out of bounds
+fn buffer(r: &Registers, stack: &Stack, offset: u8, len: u8) -> String {
+    match guest_buffer(r, stack, offset, len) {
+        Some(range) => quoted(&stack.memory()[range]),
+        None => format!("sp+{offset}"),
+    }
+}
+
+fn quoted(data: &[u8]) -> String {
+    let shown = &data[..data.len().min(MAX_LOGGED_BYTES)];
+    let ellipsis = if shown.len() < data.len() { "..." } else { "" };
+    format!("\"{}\"{ellipsis}", shown.escape_ascii())
+}
@@ -1,9 +1,31 @@
 use std::arch::asm;
 
-use crate::{registers::Registers, Stack};
+use crate::{
+    registers::{Register, Registers},
+    Stack, VmErrorKind,
+};
+
+use super::{errno, guest_buffer, guest_c_str, SyscallHandler, SyscallOutcome};
+
+/// System calls passed straight to the x86_64 linux host
+#[derive(Debug, Default)]
+pub struct X86_64Linux;
+
+impl SyscallHandler for X86_64Linux {
+    fn syscall(
+        &mut self,
+        registers: &mut Registers,
+        stack: &mut Stack,
+    ) -> Result<SyscallOutcome, VmErrorKind> {
+        vm_syscall(registers, stack)
+    }
+}
 
 /// Sycall interface, "return" value will be in r0
-pub fn vm_syscall(register: &mut Registers, stack: &mut Stack) {
+pub fn vm_syscall(
+    register: &mut Registers,
+    stack: &mut Stack,
+) -> Result<SyscallOutcome, VmErrorKind> {
     // x86_64 syscall table -> smol is mapping
     // argument | x64 reg | smol reg
     // ---------|---------|----------
@@ -17,37 +39,126 @@ pub fn vm_syscall(register: &mut Registers, stack: &mut Stack) {
     //
     // note that the ID is the number of the system call
     // the return value of the system call will be in rax (r0)
-    unsafe {
-        match register.r0 {
-            1 => vm_syscall_write(register, stack),
-            60 => vm_syscall_exit(register),
-            id => panic!("System call with id: '{id}' is not implemented for x86_64 linux"),
+    //
+    // Pointers are offsets from sp and every buffer is checked against the memory
+    // before the host sees it, a bad buffer returns -EFAULT without calling the host.
+    // Arguments that don't fit 8 bits are in l0/l1 and results that don't fit r0
+    // are also stored in l0:
+    // name   | ID  | arguments
+    // -------|-----|-------------------------------------------
+    // read   | 0   | fd r1, buffer r2, length r3
+    // write  | 1   | fd r1, buffer r2, length r3
+    // open   | 2   | NUL terminated path r1, flags l0, mode l1
+    // close  | 3   | fd r1
+    // lseek  | 8   | fd r1, offset l0 (signed), whence r3, new offset in l0
+    // getpid | 39  | pid in l0
+    // exit   | 60  | code r1, stops the VM and not the host
+    // time   | 201 | 8 byte buffer r1 if not 0, seconds in l0
+    let result = match register[Register::R0] as u8 {
+        0 | 1 => {
+            let Some(range) = guest_buffer(
+                register,
+                stack,
+                register[Register::R2] as u8,
+                register[Register::R3] as u8,
+            ) else {
+                register.set(Register::R0, errno::EFAULT.wrapping_neg().into());
+                return Ok(SyscallOutcome::Continue);
+            };
+            let data = stack.memory_mut()[range].as_mut_ptr();
+            unsafe {
+                host_syscall(
+                    register[Register::R0] as i64,
+                    [
+                        register[Register::R1] as i64,
+                        data as i64,
+                        register[Register::R3] as i64,
+                    ],
+                )
+            }
         }
-    }
-}
-
-unsafe fn vm_syscall_write(register: &mut Registers, stack: &mut Stack) {
-    let mut out: i64;
-    let sp = register.sp + register.r2 as u16;
-    let data = stack.from_sp_mut(sp).as_mut_ptr();
-    asm!(
-        "mov rax, 1",
-        "syscall",
-        in("rdi") register.r1 as i64,
-        in("rsi") data,
-        in("rdx") register.r3 as i64,
-        lateout("rax") out,
-    );
-    register.r0 = out as u8;
+        2 => {
+            let Some(range) = guest_c_str(register, stack, register[Register::R1] as u8) else {
+                register.set(Register::R0, errno::EFAULT.wrapping_neg().into());
+                return Ok(SyscallOutcome::Continue);
+            };
+            let path = stack.memory()[range].as_ptr();
+            unsafe {
+                host_syscall(
+                    2,
+                    [
+                        path as i64,
+                        register[Register::L0] as i64,
+                        register[Register::L1] as i64,
+                    ],
+                )
+            }
+        }
+        3 => unsafe { host_syscall(3, [register[Register::R1] as i64, 0, 0]) },
+        8 => {
+            let offset = register[Register::L0] as i16 as i64;
+            let result = unsafe {
+                host_syscall(
+                    8,
+                    [
+                        register[Register::R1] as i64,
+                        offset,
+                        register[Register::R3] as i64,
+                    ],
+                )
+            };
+            if result >= 0 {
+                register.set(Register::L0, result as u16);
+            }
+            result
+        }
+        39 => {
+            let pid = unsafe { host_syscall(39, [0; 3]) };
+            register.set(Register::L0, pid as u16);
+            pid
+        }
+        // Exiting the host would take down whoever embeds the VM,
+        // the caller of Vm::run decides what to do with the exit code
+        60 => return Ok(SyscallOutcome::Exit(register[Register::R1] as u8)),
+        201 => {
+            let buffer = match register[Register::R1] as u8 {
+                0 => None,
+                offset => match guest_buffer(register, stack, offset, 8) {
+                    Some(range) => Some(range),
+                    None => {
+                        register.set(Register::R0, errno::EFAULT.wrapping_neg().into());
+                        return Ok(SyscallOutcome::Continue);
+                    }
+                },
+            };
+            let seconds = unsafe { host_syscall(201, [0; 3]) };
+            if let Some(range) = buffer {
+                stack.memory_mut()[range].copy_from_slice(&seconds.to_le_bytes());
+            }
+            register.set(Register::L0, seconds as u16);
+            seconds
+        }
+        id => return Err(VmErrorKind::UnknownSyscall(id)),
+    };
+    register.set(Register::R0, result as u8 as u16);
+    Ok(SyscallOutcome::Continue)
 }
 
-unsafe fn vm_syscall_exit(register: &mut Registers) {
-    let mut out: i64;
+/// Raw host system call with up to three arguments
+///
+/// # Safety
+/// Any pointer in the arguments has to be valid for the system call
+unsafe fn host_syscall(id: i64, args: [i64; 3]) -> i64 {
+    let out: i64;
     asm!(
-        "mov rax, 60",
         "syscall",
-        in("rdi") register.r1 as i64,
-        lateout("rax") out,
+        inlateout("rax") id => out,
+        in("rdi") args[0],
+        in("rsi") args[1],
+        in("rdx") args[2],
+        lateout("rcx") _,
+        lateout("r11") _,
+        options(nostack),
     );
-    register.r0 = out as u8;
+    out
 }
@@ -1,57 +1,57 @@
-use smol_vm::Vm;
+use smol_vm::{flags, Register, Vm};
 
 #[test]
 pub fn it_adds_r0_r1() {
     let mut vm = Vm::default();
-    vm.registers.r0 = 1;
-    vm.registers.r1 = 2;
+    vm.registers.set(Register::R0, 1);
+    vm.registers.set(Register::R1, 2);
     vm.instructions.instructions = vec![
         // ALU Add from Register
         0b00_000_0_0_0,
         // Registers r0 and r1
         0b0001_0000,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.r0, 3);
+    assert_eq!(vm.registers[Register::R0], 3);
 }
 
 #[test]
 pub fn it_adds_r2_r3() {
     let mut vm = Vm::default();
-    vm.registers.r2 = 2;
-    vm.registers.r3 = 6;
+    vm.registers.set(Register::R2, 2);
+    vm.registers.set(Register::R3, 6);
     vm.instructions.instructions = vec![
         // ALU Add from Register
         0b00_000_0_0_0,
         // Registers r2 and r3
         0b0011_0010,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.r2, 8);
+    assert_eq!(vm.registers[Register::R2], 8);
 }
 
 #[test]
 pub fn it_adds_r7_r4() {
     let mut vm = Vm::default();
-    vm.registers.r4 = 11;
-    vm.registers.r7 = 100;
+    vm.registers.set(Register::R4, 11);
+    vm.registers.set(Register::R7, 100);
     vm.instructions.instructions = vec![
         // ALU Add from Register
         0b00_000_0_0_0,
         // Registers r7 and r4
         0b0100_0111,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.r7, 111);
+    assert_eq!(vm.registers[Register::R7], 111);
 }
 
 #[test]
 pub fn it_iadds_r7_123() {
     let mut vm = Vm::default();
-    vm.registers.r7 = 100;
+    vm.registers.set(Register::R7, 100);
     vm.instructions.instructions = vec![
         // ALU Add from Immediate
         0b00_000_1_0_0,
@@ -60,290 +60,573 @@ pub fn it_iadds_r7_123() {
         // Immediate 11
         11,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.r7, 111);
+    assert_eq!(vm.registers[Register::R7], 111);
 }
 
 #[test]
 pub fn it_substracts_r0_r1() {
     let mut vm = Vm::default();
-    vm.registers.r0 = 5;
-    vm.registers.r1 = 2;
+    vm.registers.set(Register::R0, 5);
+    vm.registers.set(Register::R1, 2);
     vm.instructions.instructions = vec![
         // ALU Subtract from Register
         0b00_001_0_0_0,
         // Registers r0 and r1
         0b0001_0000,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.r0, 3);
+    assert_eq!(vm.registers[Register::R0], 3);
 }
 
 #[test]
 pub fn it_substracts_r2_r3() {
     let mut vm = Vm::default();
-    vm.registers.r2 = 14;
-    vm.registers.r3 = 6;
+    vm.registers.set(Register::R2, 14);
+    vm.registers.set(Register::R3, 6);
     vm.instructions.instructions = vec![
         // ALU Subtract from Register
         0b00_001_0_0_0,
         // Registers r2 and r3
         0b0011_0010,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.r2, 8);
+    assert_eq!(vm.registers[Register::R2], 8);
 }
 
 #[test]
 pub fn it_substracts_r7_r4() {
     let mut vm = Vm::default();
-    vm.registers.r4 = 100;
-    vm.registers.r7 = 211;
+    vm.registers.set(Register::R4, 100);
+    vm.registers.set(Register::R7, 211);
     vm.instructions.instructions = vec![
         // ALU Subtract from Register
         0b00_001_0_0_0,
         // Registers r7 and r4
         0b0100_0111,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.r7, 111);
+    assert_eq!(vm.registers[Register::R7], 111);
 }
 
 #[test]
 pub fn it_binary_ands_r0_r1() {
     let mut vm = Vm::default();
-    vm.registers.r0 = 0b101;
-    vm.registers.r1 = 0b100;
+    vm.registers.set(Register::R0, 0b101);
+    vm.registers.set(Register::R1, 0b100);
     vm.instructions.instructions = vec![
         // ALU Binary and from Register
         0b00_010_0_0_0,
         // Registers r0 and r1
         0b0001_0000,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.r0, 4);
+    assert_eq!(vm.registers[Register::R0], 4);
 }
 
 #[test]
 pub fn it_binary_ands_r2_r3() {
     let mut vm = Vm::default();
-    vm.registers.r2 = 14;
-    vm.registers.r3 = 8;
+    vm.registers.set(Register::R2, 14);
+    vm.registers.set(Register::R3, 8);
     vm.instructions.instructions = vec![
         // ALU Binary and from Register
         0b00_010_0_0_0,
         // Registers r2 and r3
         0b0011_0010,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.r2, 8);
+    assert_eq!(vm.registers[Register::R2], 8);
 }
 
 #[test]
 pub fn it_binary_ands_r7_r4() {
     let mut vm = Vm::default();
-    vm.registers.r4 = 0b01111111;
-    vm.registers.r7 = 0b11101111;
+    vm.registers.set(Register::R4, 0b01111111);
+    vm.registers.set(Register::R7, 0b11101111);
     vm.instructions.instructions = vec![
         // ALU Binary and from Register
         0b00_010_0_0_0,
         // Registers r7 and r4
         0b0100_0111,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.r7, 111);
+    assert_eq!(vm.registers[Register::R7], 111);
 }
 
 #[test]
 pub fn it_binary_ors_r0_r1() {
     let mut vm = Vm::default();
-    vm.registers.r0 = 0b101;
-    vm.registers.r1 = 0b100;
+    vm.registers.set(Register::R0, 0b101);
+    vm.registers.set(Register::R1, 0b100);
     vm.instructions.instructions = vec![
         // ALU Binary or from Register
         0b00_011_0_0_0,
         // Registers r0 and r1
         0b0001_0000,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.r0, 5);
+    assert_eq!(vm.registers[Register::R0], 5);
 }
 
 #[test]
 pub fn it_binary_ors_r2_r3() {
     let mut vm = Vm::default();
-    vm.registers.r2 = 14;
-    vm.registers.r3 = 8;
+    vm.registers.set(Register::R2, 14);
+    vm.registers.set(Register::R3, 8);
     vm.instructions.instructions = vec![
         // ALU Binary or from Register
         0b00_011_0_0_0,
         // Registers r2 and r3
         0b0011_0010,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.r2, 14);
+    assert_eq!(vm.registers[Register::R2], 14);
 }
 
 #[test]
 pub fn it_binary_ors_r7_r4() {
     let mut vm = Vm::default();
-    vm.registers.r4 = 0b01101111;
-    vm.registers.r7 = 0b01101110;
+    vm.registers.set(Register::R4, 0b01101111);
+    vm.registers.set(Register::R7, 0b01101110);
     vm.instructions.instructions = vec![
         // ALU Binary or from Register
         0b00_011_0_0_0,
         // Registers r7 and r4
         0b0100_0111,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.r7, 111);
+    assert_eq!(vm.registers[Register::R7], 111);
 }
 
 #[test]
 pub fn it_binary_xors_r0_r1() {
     let mut vm = Vm::default();
-    vm.registers.r0 = 0b101;
-    vm.registers.r1 = 0b100;
+    vm.registers.set(Register::R0, 0b101);
+    vm.registers.set(Register::R1, 0b100);
     vm.instructions.instructions = vec![
         // ALU Binary xor from Register
         0b00_100_0_0_0,
         // Registers r0 and r1
         0b0001_0000,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.r0, 1);
+    assert_eq!(vm.registers[Register::R0], 1);
 }
 
 #[test]
 pub fn it_binary_xors_r2_r3() {
     let mut vm = Vm::default();
-    vm.registers.r2 = 14;
-    vm.registers.r3 = 8;
+    vm.registers.set(Register::R2, 14);
+    vm.registers.set(Register::R3, 8);
     vm.instructions.instructions = vec![
         // ALU Binary xor from Register
         0b00_100_0_0_0,
         // Registers r2 and r3
         0b0011_0010,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.r2, 6);
+    assert_eq!(vm.registers[Register::R2], 6);
 }
 
 #[test]
 pub fn it_binary_xors_r7_r4() {
     let mut vm = Vm::default();
-    vm.registers.r4 = 0b01101111;
-    vm.registers.r7 = 0b01101110;
+    vm.registers.set(Register::R4, 0b01101111);
+    vm.registers.set(Register::R7, 0b01101110);
     vm.instructions.instructions = vec![
         // ALU Binary xor from Register
         0b00_100_0_0_0,
         // Registers r7 and r4
         0b0100_0111,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.r7, 1);
+    assert_eq!(vm.registers[Register::R7], 1);
 }
 
 #[test]
 pub fn it_binary_nots_r0() {
     // TODO: fix this since it's not working due to u8 -> u16 stuffs
     let mut vm = Vm::default();
-    vm.registers.r0 = 0b1111_1101;
+    vm.registers.set(Register::R0, 0b1111_1101);
     vm.instructions.instructions = vec![
         // ALU Binary not from Register
         0b00_101_0_0_0,
         // Register r0
         0b000_0000,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.r0, 0b0000010);
+    assert_eq!(vm.registers[Register::R0], 0b0000010);
 }
 
 #[test]
 pub fn it_binary_nots_r2() {
     let mut vm = Vm::default();
-    vm.registers.r2 = 123;
+    vm.registers.set(Register::R2, 123);
     vm.instructions.instructions = vec![
         // ALU Binary not from Register
         0b00_101_0_0_0,
         // Register r2
         0b0000_0010,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.r2, 132);
+    assert_eq!(vm.registers[Register::R2], 132);
 }
 
 #[test]
 pub fn it_binary_nots_r7() {
     let mut vm = Vm::default();
-    vm.registers.r7 = 0b0110_1111;
+    vm.registers.set(Register::R7, 0b0110_1111);
     vm.instructions.instructions = vec![
         // ALU Binary not from Register
         0b00_101_0_0_0,
         // Register r 7
         0b0000_0111,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.r7, 0b1001_0000);
+    assert_eq!(vm.registers[Register::R7], 0b1001_0000);
 }
 
 #[test]
 pub fn it_increments_r0() {
     let mut vm = Vm::default();
-    vm.registers.r0 = 0b111101;
+    vm.registers.set(Register::R0, 0b111101);
     vm.instructions.instructions = vec![
         // ALU Incerement from Register
         0b00_111_0_0_0,
         // Register r0
         0b000_0000,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.r0, 0b111110);
+    assert_eq!(vm.registers[Register::R0], 0b111110);
 }
 
 #[test]
 pub fn it_increments_r2() {
     let mut vm = Vm::default();
-    vm.registers.r2 = 123;
+    vm.registers.set(Register::R2, 123);
     vm.instructions.instructions = vec![
         // ALU Incerement from Register
         0b00_111_0_0_0,
         // Register r2
         0b0000_0010,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.r2, 124);
+    assert_eq!(vm.registers[Register::R2], 124);
 }
 
 #[test]
 pub fn it_decrements_r7() {
     let mut vm = Vm::default();
-    vm.registers.r7 = 0b01101111;
+    vm.registers.set(Register::R7, 0b01101111);
     vm.instructions.instructions = vec![
         // ALU Decrement from Register
         0b00_111_1_0_0,
         // Register r 7
         0b0000_0111,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.r7, 0b01101110);
+    assert_eq!(vm.registers[Register::R7], 0b01101110);
+}
+
+#[test]
+pub fn it_compares_equal_r0_r1() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::R0, 7);
+    vm.registers.set(Register::R1, 7);
+    vm.instructions.instructions = vec![
+        // ALU Equality from Register without writing the result
+        0b00_110_0_0_1,
+        // Registers r0 and r1
+        0b0001_0000,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::Fg], flags::ZERO);
+    assert_eq!(vm.registers[Register::R0], 7);
+}
+
+#[test]
+pub fn it_compares_less_r2_r3() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::R2, 3);
+    vm.registers.set(Register::R3, 200);
+    vm.instructions.instructions = vec![
+        // ALU Equality from Register without writing the result
+        0b00_110_0_0_1,
+        // Registers r2 and r3
+        0b0011_0010,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::Fg], flags::LESS | flags::CARRY);
+    assert_eq!(vm.registers[Register::R2], 3);
+}
+
+#[test]
+pub fn it_compares_greater_immediate() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::R7, 100);
+    vm.instructions.instructions = vec![
+        // ALU Equality from Immediate without writing the result
+        0b00_110_1_0_1,
+        // Register r7
+        0b0000_0111,
+        // Immediate 60
+        60,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::Fg], flags::GREATER);
+    assert_eq!(vm.registers[Register::R7], 100);
+}
+
+#[test]
+pub fn it_writes_equality_result() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::R0, 60);
+    vm.registers.set(Register::R1, 61);
+    vm.instructions.instructions = vec![
+        // ALU Equality from Immediate
+        0b00_110_1_0_0,
+        // Register r0
+        0b0000_0000,
+        // Immediate 60
+        60,
+        // ALU Equality from Register
+        0b00_110_0_0_0,
+        // Registers r1 and r1
+        0b0001_0001,
+        // ALU Equality from Immediate
+        0b00_110_1_0_0,
+        // Register r2
+        0b0000_0010,
+        // Immediate 1
+        1,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 1);
+    assert_eq!(vm.registers[Register::R1], 1);
+    assert_eq!(vm.registers[Register::R2], 0);
+    assert_eq!(
+        vm.registers[Register::Fg],
+        flags::LESS | flags::CARRY | flags::SIGN
+    );
+}
+
+#[test]
+pub fn it_loops_until_equal() {
+    let mut vm = Vm::default();
+    vm.instructions.instructions = vec![
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r0
+        0b0000_0000,
+        // ALU Equality from Immediate without writing the result
+        0b00_110_1_0_1,
+        // Register r0
+        0b0000_0000,
+        // Immediate 5
+        5,
+        // Branch if not equal 8 bit offset
+        0b11_010_0_00,
+        // Offset of -5
+        0b1111_1011,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 5);
+    assert_eq!(vm.registers[Register::Fg], flags::ZERO);
+}
+
+#[test]
+pub fn it_wraps_add_with_carry() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::R0, 200);
+    vm.registers.set(Register::R1, 100);
+    vm.instructions.instructions = vec![
+        // ALU Add from Register
+        0b00_000_0_0_0,
+        // Registers r0 and r1
+        0b0001_0000,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 44);
+    assert_eq!(vm.registers[Register::Fg], flags::CARRY);
+}
+
+#[test]
+pub fn it_sets_signed_overflow_on_add() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::R0, 100);
+    vm.registers.set(Register::R1, 100);
+    vm.instructions.instructions = vec![
+        // ALU Add from Register
+        0b00_000_0_0_0,
+        // Registers r0 and r1
+        0b0001_0000,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 200);
+    assert_eq!(vm.registers[Register::Fg], flags::OVERFLOW | flags::SIGN);
+}
+
+#[test]
+pub fn it_wraps_add_to_zero() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::R0, 255);
+    vm.instructions.instructions = vec![
+        // ALU Add from Immediate
+        0b00_000_1_0_0,
+        // Register r0
+        0b0000_0000,
+        // Immediate 1
+        1,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 0);
+    assert_eq!(vm.registers[Register::Fg], flags::ZERO | flags::CARRY);
+}
+
+#[test]
+pub fn it_wraps_16bit_add() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::L0, 0xffff);
+    vm.registers.set(Register::L1, 2);
+    vm.instructions.instructions = vec![
+        // ALU Add from Register
+        0b00_000_0_0_0,
+        // Registers l0 and l1
+        0b1010_1001,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::L0], 1);
+    assert_eq!(vm.registers[Register::Fg], flags::CARRY);
+}
+
+#[test]
+pub fn it_borrows_on_subtract() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::R0, 1);
+    vm.registers.set(Register::R1, 2);
+    vm.instructions.instructions = vec![
+        // ALU Subtract from Register
+        0b00_001_0_0_0,
+        // Registers r0 and r1
+        0b0001_0000,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 255);
+    assert_eq!(vm.registers[Register::Fg], flags::CARRY | flags::SIGN);
+}
+
+#[test]
+pub fn it_sets_signed_overflow_on_subtract() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::R0, 0x80);
+    vm.registers.set(Register::R1, 1);
+    vm.instructions.instructions = vec![
+        // ALU Subtract from Register
+        0b00_001_0_0_0,
+        // Registers r0 and r1
+        0b0001_0000,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 0x7f);
+    assert_eq!(vm.registers[Register::Fg], flags::OVERFLOW);
+}
+
+#[test]
+pub fn it_sets_zero_on_binary_and() {
+    let mut vm = Vm::default();
+    vm.registers
+        .set(Register::Fg, flags::CARRY | flags::GREATER);
+    vm.registers.set(Register::R0, 0b1010);
+    vm.registers.set(Register::R1, 0b0101);
+    vm.instructions.instructions = vec![
+        // ALU Binary and from Register
+        0b00_010_0_0_0,
+        // Registers r0 and r1
+        0b0001_0000,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 0);
+    // Carry is cleared, the comparison is kept
+    assert_eq!(vm.registers[Register::Fg], flags::ZERO | flags::GREATER);
+}
+
+#[test]
+pub fn it_sets_sign_on_binary_not() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::R0, 1);
+    vm.instructions.instructions = vec![
+        // ALU Binary not from Register
+        0b00_101_0_0_0,
+        // Register r0
+        0b0000_0000,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 0b1111_1110);
+    assert_eq!(vm.registers[Register::Fg], flags::SIGN);
+}
+
+#[test]
+pub fn it_wraps_decrement_of_zero() {
+    let mut vm = Vm::default();
+    vm.instructions.instructions = vec![
+        // ALU Decrement from Register
+        0b00_111_1_0_0,
+        // Register r3
+        0b0000_0011,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R3], 255);
+    assert_eq!(vm.registers[Register::Fg], flags::CARRY | flags::SIGN);
+}
+
+#[test]
+pub fn it_only_sets_flags_with_noop() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::R0, 5);
+    vm.instructions.instructions = vec![
+        // ALU Subtract from Immediate without writing the result
+        0b00_001_1_0_1,
+        // Register r0
+        0b0000_0000,
+        // Immediate 5
+        5,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 5);
+    assert_eq!(vm.registers[Register::Fg], flags::ZERO);
 }
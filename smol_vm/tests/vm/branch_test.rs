@@ -0,0 +1,222 @@
+use smol_vm::{flags, Register, Vm, VmErrorKind};
+
+#[test]
+pub fn it_jumps_forward() {
+    let mut vm = Vm::default();
+    vm.instructions.instructions = vec![
+        // Relative jump 8 bit offset
+        0b11_000_0_00,
+        // Skip over the increment
+        4,
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r0
+        0b0000_0000,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 0);
+    assert_eq!(vm.registers[Register::Ic], 4);
+}
+
+#[test]
+pub fn it_jumps_backward_16bit() {
+    let mut vm = Vm::default();
+    vm.instructions.instructions = vec![
+        // Relative jump 8 bit offset
+        0b11_000_0_00,
+        // Jump to the 16 bit jump
+        7,
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r0
+        0b0000_0000,
+        // Relative jump 8 bit offset
+        0b11_000_0_00,
+        // Jump to the end
+        6,
+        // Padding that should never be executed
+        0b11_111_0_00,
+        // Relative jump 16 bit offset
+        0b11_000_1_00,
+        // Offset of -5 in 16 bit little endian
+        0b1111_1011,
+        0b1111_1111,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 1);
+    assert_eq!(vm.registers[Register::Ic], 10);
+}
+
+#[test]
+pub fn it_branches_if_equal() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::Fg, flags::ZERO);
+    vm.instructions.instructions = vec![
+        // Branch if equal 8 bit offset
+        0b11_001_0_00,
+        // Skip over the increment
+        4,
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r0
+        0b0000_0000,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 0);
+}
+
+#[test]
+pub fn it_does_not_branch_if_not_equal() {
+    let mut vm = Vm::default();
+    vm.instructions.instructions = vec![
+        // Branch if equal 8 bit offset
+        0b11_001_0_00,
+        // Skip over the increment
+        4,
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r0
+        0b0000_0000,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 1);
+}
+
+#[test]
+pub fn it_branches_if_not_equal() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::Fg, flags::GREATER);
+    vm.instructions.instructions = vec![
+        // Branch if not equal 8 bit offset
+        0b11_010_0_00,
+        // Skip over the increment
+        4,
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r0
+        0b0000_0000,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 0);
+}
+
+#[test]
+pub fn it_branches_if_greater_than() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::Fg, flags::GREATER);
+    vm.instructions.instructions = vec![
+        // Branch if greater than 8 bit offset
+        0b11_011_0_00,
+        // Skip over the increment
+        4,
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r0
+        0b0000_0000,
+        // Branch if less than 8 bit offset
+        0b11_100_0_00,
+        // Skip over the increment
+        4,
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r1
+        0b0000_0001,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 0);
+    assert_eq!(vm.registers[Register::R1], 1);
+}
+
+#[test]
+pub fn it_branches_if_less_than() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::Fg, flags::LESS);
+    vm.instructions.instructions = vec![
+        // Branch if greater than 8 bit offset
+        0b11_011_0_00,
+        // Skip over the increment
+        4,
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r0
+        0b0000_0000,
+        // Branch if less than 8 bit offset
+        0b11_100_0_00,
+        // Skip over the increment
+        4,
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r1
+        0b0000_0001,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 1);
+    assert_eq!(vm.registers[Register::R1], 0);
+}
+
+#[test]
+pub fn it_keeps_the_comparison_across_alu_operations() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::R0, 3);
+    vm.registers.set(Register::R1, 10);
+    vm.instructions.instructions = vec![
+        // ALU Equality from Register without writing the result
+        0b00_110_0_0_1,
+        // Registers r1 and r0
+        0b0001_0000,
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r2
+        0b0000_0010,
+        // Branch if less than 8 bit offset
+        0b11_100_0_00,
+        // Skip over the increment
+        4,
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r3
+        0b0000_0011,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R2], 1);
+    assert_eq!(vm.registers[Register::R3], 0);
+    assert_eq!(vm.registers[Register::Fg], flags::LESS);
+}
+
+#[test]
+pub fn it_rejects_jumps_outside_instructions() {
+    let mut vm = Vm::default();
+    vm.instructions.instructions = vec![
+        // Relative jump 8 bit offset
+        0b11_000_0_00,
+        // Jump past the end
+        3,
+    ];
+    let err = vm.run().unwrap_err();
+
+    assert_eq!(err.kind, VmErrorKind::BranchOutOfRange { target: 3 });
+    assert_eq!(err.ic, 0);
+}
+
+#[test]
+pub fn it_rejects_jumps_before_instructions() {
+    let mut vm = Vm::default();
+    vm.instructions.instructions = vec![
+        // Relative jump 8 bit offset
+        0b11_000_0_00,
+        // Offset of -1
+        0b1111_1111,
+    ];
+    let err = vm.run().unwrap_err();
+
+    assert_eq!(err.kind, VmErrorKind::BranchOutOfRange { target: -1 });
+    assert_eq!(err.ic, 0);
+}
@@ -0,0 +1,166 @@
+use smol_vm::{
+    syscall::{Emulated, Sandbox},
+    Comparison, Condition, ExitReason, MemoryAccess, Register, StopReason, WatchKind, Watchpoint,
+};
+
+use super::vm_with;
+
+/// Increments r0 and pushes it 3 times, then pops it back
+const LOOP: &[u8] = &[
+    // ALU Increment from Register
+    0b00_111_0_0_0,
+    // Register r0
+    0b0000_0000,
+    // Stack Push 8-bit Register
+    0b10_00_00_00,
+    // Register r0
+    0b0000_0000,
+    // ALU Compare Immediate
+    0b00_110_1_0_1,
+    // Register r0
+    0b0000_0000,
+    3,
+    // Branch Not Equal 8-bit offset -7
+    0b11_010_000,
+    (-7i8) as u8,
+    // Stack Pop 8-bit Register
+    0b10_01_00_00,
+    // Register r1
+    0b0000_0001,
+];
+
+fn stopped(reason: StopReason) -> ExitReason {
+    ExitReason::Stopped { reason }
+}
+
+#[test]
+pub fn it_stops_at_breakpoints() {
+    let mut vm = vm_with(Emulated, LOOP);
+    vm.add_breakpoint(2);
+
+    assert_eq!(vm.run().unwrap(), stopped(StopReason::Breakpoint(2)));
+    assert_eq!(vm.registers[Register::Ic], 2);
+    assert_eq!(vm.registers[Register::R0], 1);
+    assert_eq!(vm.registers[Register::Sp], 0);
+
+    // Continues past the breakpoint it stopped at
+    assert_eq!(vm.run().unwrap(), stopped(StopReason::Breakpoint(2)));
+    assert_eq!(vm.registers[Register::R0], 2);
+    assert_eq!(vm.registers[Register::Sp], 1);
+
+    assert!(vm.remove_breakpoint(2));
+    assert!(!vm.remove_breakpoint(2));
+    assert_eq!(vm.run().unwrap(), ExitReason::EndOfInstructions);
+    assert_eq!(vm.registers[Register::R1], 3);
+}
+
+#[test]
+pub fn it_stops_at_the_first_instruction() {
+    let mut vm = vm_with(Emulated, LOOP);
+    vm.add_breakpoint(0);
+
+    assert_eq!(vm.run().unwrap(), stopped(StopReason::Breakpoint(0)));
+    assert_eq!(vm.registers[Register::R0], 0);
+}
+
+#[test]
+pub fn it_stops_at_conditional_breakpoints() {
+    let mut vm = vm_with(Emulated, LOOP);
+    let condition: Condition = "r0 == 2".parse().unwrap();
+    assert_eq!(condition, Condition::new(Register::R0, Comparison::Eq, 2));
+    vm.add_conditional_breakpoint(2, condition);
+
+    assert_eq!(vm.run().unwrap(), stopped(StopReason::Breakpoint(2)));
+    assert_eq!(vm.registers[Register::R0], 2);
+    assert_eq!(vm.run().unwrap(), ExitReason::EndOfInstructions);
+    assert_eq!(vm.breakpoints(), [(2, Some(condition))]);
+}
+
+#[test]
+pub fn it_parses_conditions() {
+    let condition: Condition = "sp>=0x10".parse().unwrap();
+    assert_eq!(condition, Condition::new(Register::Sp, Comparison::Ge, 16));
+    assert_eq!(condition.to_string(), "sp >= 16");
+
+    assert!("r0 = 1".parse::<Condition>().is_err());
+    assert!("r9 == 1".parse::<Condition>().is_err());
+    assert!("r0 == x".parse::<Condition>().is_err());
+}
+
+#[test]
+pub fn it_stops_at_write_watchpoints() {
+    let mut vm = vm_with(Emulated, LOOP);
+    let watchpoint = Watchpoint::new(1..2, WatchKind::Write);
+    vm.add_watchpoint(watchpoint.clone());
+
+    let reason = StopReason::Watchpoint {
+        watchpoint: watchpoint.clone(),
+        access: MemoryAccess::Write {
+            address: 1,
+            old: vec![0],
+            new: vec![2],
+        },
+    };
+    assert_eq!(vm.run().unwrap(), stopped(reason));
+    // Stops after the instruction that wrote
+    assert_eq!(vm.registers[Register::Ic], 4);
+    assert_eq!(vm.registers[Register::Sp], 2);
+
+    assert_eq!(vm.run().unwrap(), ExitReason::EndOfInstructions);
+    assert!(vm.remove_watchpoint(&watchpoint));
+    assert!(vm.watchpoints().is_empty());
+}
+
+#[test]
+pub fn it_stops_at_read_watchpoints() {
+    let mut vm = vm_with(Emulated, LOOP);
+    vm.add_watchpoint(Watchpoint::new(0..3, WatchKind::Read));
+
+    let ExitReason::Stopped { reason } = vm.run().unwrap() else {
+        panic!("watchpoint did not trigger");
+    };
+    let StopReason::Watchpoint { access, .. } = reason else {
+        panic!("stopped at a breakpoint");
+    };
+    assert_eq!(
+        access,
+        MemoryAccess::Read {
+            address: 2,
+            bytes: vec![3]
+        }
+    );
+    assert_eq!(vm.registers[Register::R1], 3);
+}
+
+#[test]
+pub fn it_stops_at_read_watchpoints_of_syscalls() {
+    let mut vm = vm_with(
+        Sandbox::new(),
+        &[
+            // Systemcall
+            0b11_101_111,
+            // ALU Increment from Register
+            0b00_111_0_0_0,
+            // Register r7
+            0b0000_0111,
+        ],
+    );
+    vm.registers.set(Register::R0, 1);
+    vm.registers.set(Register::R1, 1);
+    vm.registers.set(Register::R2, 4);
+    vm.registers.set(Register::R3, 3);
+    vm.stack.memory_mut()[4..7].copy_from_slice(b"hi\n");
+    let watchpoint = Watchpoint::new(6..7, WatchKind::Read);
+    vm.add_watchpoint(watchpoint.clone());
+
+    let reason = StopReason::Watchpoint {
+        watchpoint,
+        access: MemoryAccess::Read {
+            address: 4,
+            bytes: b"hi\n".to_vec(),
+        },
+    };
+    assert_eq!(vm.run().unwrap(), stopped(reason));
+    assert_eq!(vm.registers[Register::Ic], 1);
+    assert_eq!(vm.registers[Register::R0], 3);
+}
@@ -0,0 +1,101 @@
+use smol_vm::{Register, Vm, VmErrorKind};
+
+#[test]
+pub fn it_calls_and_returns() {
+    let mut vm = Vm::default();
+    vm.instructions.instructions = vec![
+        // Call 8 bit offset
+        0b11_101_0_00,
+        // Call the increment routine
+        6,
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r1
+        0b0000_0001,
+        // Relative jump 8 bit offset
+        0b11_000_0_00,
+        // Jump to the end
+        5,
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r0
+        0b0000_0000,
+        // Return from call
+        0b11_110_0_00,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 1);
+    assert_eq!(vm.registers[Register::R1], 1);
+    assert_eq!(vm.registers[Register::Cr], 0);
+    assert_eq!(vm.registers[Register::Sp], 0);
+}
+
+#[test]
+pub fn it_saves_return_address_in_cr() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::Cr, 0x1234);
+    vm.instructions.instructions = vec![
+        // Call 16 bit offset
+        0b11_101_1_00,
+        // Call the end of the program
+        3,
+        0,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::Cr], 3);
+    assert_eq!(vm.registers[Register::Sp], 2);
+    // The previous call register is spilled to the stack
+    assert_eq!(vm.stack.stack()[..2], [0x34, 0x12]);
+}
+
+#[test]
+pub fn it_nests_calls() {
+    let mut vm = Vm::default();
+    vm.instructions.instructions = vec![
+        // Call 8 bit offset
+        0b11_101_0_00,
+        // Call the outer routine
+        4,
+        // Relative jump 8 bit offset
+        0b11_000_0_00,
+        // Jump to the end
+        10,
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r0
+        0b0000_0000,
+        // Call 8 bit offset
+        0b11_101_0_00,
+        // Call the inner routine
+        3,
+        // Return from call
+        0b11_110_0_00,
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r1
+        0b0000_0001,
+        // Return from call
+        0b11_110_0_00,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 1);
+    assert_eq!(vm.registers[Register::R1], 1);
+    assert_eq!(vm.registers[Register::Ic], 12);
+    assert_eq!(vm.registers[Register::Sp], 0);
+}
+
+#[test]
+pub fn it_rejects_return_without_call() {
+    let mut vm = Vm::default();
+    vm.instructions.instructions = vec![
+        // Return from call
+        0b11_110_0_00,
+    ];
+    let err = vm.run().unwrap_err();
+
+    assert_eq!(err.kind, VmErrorKind::StackUnderflow);
+    assert_eq!(err.ic, 0);
+}
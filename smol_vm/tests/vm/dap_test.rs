@@ -0,0 +1,287 @@
+use std::io::Cursor;
+
+use smol_file::{DebugInfo, LineEntry, SmolFile, Storage, StorageItem, VariableInfo};
+use smol_vm::dap::{read_message, write_message, DapServer, Json};
+
+/// Writes `hi\n` from the `msg` variable, one instruction per line
+fn write_program(path: &str) {
+    let file = SmolFile {
+        storage: Storage {
+            total_size: 7,
+            items: vec![StorageItem {
+                // Initialised flag and size of 3
+                size: 0x8003,
+                offset: 0,
+                init_data: Some(b"hi\n".to_vec()),
+            }],
+        },
+        instructions: vec![
+            // ALU Add Immediate to r0
+            0b00_000_1_0_0,
+            0,
+            1,
+            // ALU Add Immediate to r1
+            0b00_000_1_0_0,
+            1,
+            1,
+            // ALU Add Immediate to r3
+            0b00_000_1_0_0,
+            3,
+            3,
+            // Stack save variable 0
+            0b10_10_11_00,
+            0,
+            0,
+            // Systemcall
+            0b11_101_111,
+            // Unsave variable
+            0b10_11_00_00,
+        ],
+    };
+    file.save(path);
+
+    let lines = [(4, 0), (5, 3), (6, 6), (7, 9), (8, 12), (9, 13)];
+    DebugInfo {
+        source: "/src/hi.smol".to_string(),
+        lines: lines
+            .into_iter()
+            .map(|(line, ic)| LineEntry { line, ic })
+            .collect(),
+        variables: vec![VariableInfo {
+            name: "msg".to_string(),
+            address: u16::MAX / 2,
+            size: 3,
+        }],
+    }
+    .save(&DebugInfo::path_for(path));
+}
+
+fn request(seq: i64, command: &str, arguments: Json) -> Json {
+    Json::object([
+        ("seq", seq.into()),
+        ("type", "request".into()),
+        ("command", command.into()),
+        ("arguments", arguments),
+    ])
+}
+
+/// Messages sent by the server in answer to the requests
+fn session(requests: Vec<Json>) -> Vec<Json> {
+    let mut input = Vec::new();
+    for request in &requests {
+        write_message(&mut input, request).unwrap();
+    }
+    let mut output = Vec::new();
+    DapServer::new()
+        .serve(&mut Cursor::new(input), &mut output)
+        .unwrap();
+
+    let mut output = Cursor::new(output);
+    let mut messages = Vec::new();
+    while let Some(message) = read_message(&mut output).unwrap() {
+        messages.push(message);
+    }
+    messages
+}
+
+fn str_of<'a>(message: &'a Json, key: &str) -> &'a str {
+    message.get(key).and_then(Json::as_str).unwrap()
+}
+
+/// `command` of responses and `event` of events
+fn kinds(messages: &[Json]) -> Vec<String> {
+    messages
+        .iter()
+        .map(|message| match str_of(message, "type") {
+            "response" => str_of(message, "command").to_string(),
+            _ => format!("event:{}", str_of(message, "event")),
+        })
+        .collect()
+}
+
+fn variable<'a>(response: &'a Json, name: &str) -> &'a str {
+    let variables = response.get("body").unwrap().get("variables").unwrap();
+    let variable = variables
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|variable| str_of(variable, "name") == name)
+        .unwrap();
+    str_of(variable, "value")
+}
+
+#[test]
+pub fn it_round_trips_json() {
+    let text = r#"{"a":[1,-2.5,true,null],"b":"x\"\né","c":{}}"#;
+    let json = Json::parse(text).unwrap();
+
+    assert_eq!(
+        json.get("a").unwrap().as_array().unwrap()[0].as_i64(),
+        Some(1)
+    );
+    assert_eq!(json.get("b").unwrap().as_str(), Some("x\"\né"));
+    assert_eq!(Json::parse(&json.to_string()).unwrap(), json);
+    assert!(Json::parse("{\"a\":}").is_err());
+    assert!(Json::parse("[1] 2").is_err());
+}
+
+#[test]
+pub fn it_debugs_a_program() {
+    let path = std::env::temp_dir().join(format!("smol_dap_{}.obj", std::process::id()));
+    let path = path.to_str().unwrap();
+    write_program(path);
+
+    let source = Json::object([("path", "/src/hi.smol".into())]);
+    let breakpoints = vec![
+        Json::object([("line", 8.into())]),
+        Json::object([("line", 20.into())]),
+    ];
+    let messages = session(vec![
+        request(
+            1,
+            "initialize",
+            Json::object([("adapterID", "smol".into())]),
+        ),
+        request(
+            2,
+            "launch",
+            Json::object([("program", path.into()), ("stopOnEntry", true.into())]),
+        ),
+        request(
+            3,
+            "setBreakpoints",
+            Json::object([("source", source), ("breakpoints", breakpoints.into())]),
+        ),
+        request(4, "configurationDone", Json::Null),
+        request(5, "next", Json::Null),
+        request(6, "stackTrace", Json::object([("threadId", 1.into())])),
+        request(7, "continue", Json::Null),
+        request(
+            8,
+            "variables",
+            Json::object([("variablesReference", 1.into())]),
+        ),
+        request(9, "next", Json::Null),
+        request(
+            10,
+            "variables",
+            Json::object([("variablesReference", 2.into())]),
+        ),
+        request(11, "continue", Json::Null),
+        request(12, "disconnect", Json::Null),
+    ]);
+    std::fs::remove_file(path).unwrap();
+    std::fs::remove_file(DebugInfo::path_for(path)).unwrap();
+
+    assert_eq!(
+        kinds(&messages),
+        [
+            "initialize",
+            "launch",
+            "event:initialized",
+            "setBreakpoints",
+            "configurationDone",
+            "event:stopped",
+            "next",
+            "event:stopped",
+            "stackTrace",
+            "continue",
+            "event:stopped",
+            "variables",
+            "next",
+            "event:output",
+            "event:stopped",
+            "variables",
+            "continue",
+            "event:exited",
+            "event:terminated",
+            "disconnect",
+        ]
+    );
+    assert!(messages
+        .iter()
+        .all(|message| message.get("success") != Some(&false.into())));
+
+    // Line 8 has the syscall, nothing is on or after line 20
+    let breakpoints = messages[3].get("body").unwrap().get("breakpoints").unwrap();
+    let breakpoints = breakpoints.as_array().unwrap();
+    assert_eq!(breakpoints[0].get("verified"), Some(&true.into()));
+    assert_eq!(breakpoints[0].get("line").and_then(Json::as_i64), Some(8));
+    assert_eq!(breakpoints[1].get("verified"), Some(&false.into()));
+
+    assert_eq!(str_of(messages[5].get("body").unwrap(), "reason"), "entry");
+    assert_eq!(str_of(messages[7].get("body").unwrap(), "reason"), "step");
+    let frames = messages[8].get("body").unwrap().get("stackFrames").unwrap();
+    let frame = &frames.as_array().unwrap()[0];
+    assert_eq!(frame.get("line").and_then(Json::as_i64), Some(5));
+    assert_eq!(str_of(frame.get("source").unwrap(), "path"), "/src/hi.smol");
+
+    assert_eq!(
+        str_of(messages[10].get("body").unwrap(), "reason"),
+        "breakpoint"
+    );
+    assert_eq!(variable(&messages[11], "r0"), "0x01 (1)");
+    assert_eq!(variable(&messages[11], "ic"), "0x000c (12)");
+
+    let output = messages[13].get("body").unwrap();
+    assert_eq!(str_of(output, "category"), "stdout");
+    assert_eq!(str_of(output, "output"), "hi\n");
+    assert_eq!(variable(&messages[15], "msg"), "68 69 0a");
+
+    let exited = messages[17].get("body").unwrap();
+    assert_eq!(exited.get("exitCode").and_then(Json::as_i64), Some(0));
+}
+
+#[test]
+pub fn it_fails_to_launch_a_malformed_program() {
+    let path = std::env::temp_dir().join(format!("smol_dap_bad_{}.obj", std::process::id()));
+    let path = path.to_str().unwrap();
+    // Storage of 16 bytes in a 3 byte file
+    std::fs::write(path, [16, 0, 0]).unwrap();
+
+    let messages = session(vec![
+        request(1, "launch", Json::object([("program", path.into())])),
+        request(2, "threads", Json::Null),
+    ]);
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(kinds(&messages), ["launch", "threads"]);
+    assert_eq!(messages[0].get("success"), Some(&false.into()));
+    assert_eq!(
+        str_of(&messages[0], "message"),
+        format!("{path}: The storage is cut off")
+    );
+}
+
+#[test]
+pub fn it_fails_to_launch_a_program_with_variables_past_the_memory() {
+    let path = std::env::temp_dir().join(format!("smol_dap_big_{}.obj", std::process::id()));
+    let path = path.to_str().unwrap();
+    SmolFile {
+        storage: Storage {
+            total_size: 7,
+            items: vec![StorageItem {
+                // Initialised flag and size of 3
+                size: 0x8003,
+                // Last byte of the variable space
+                offset: u16::MAX / 2,
+                init_data: Some(b"hi\n".to_vec()),
+            }],
+        },
+        instructions: vec![],
+    }
+    .save(path);
+
+    let messages = session(vec![request(
+        1,
+        "launch",
+        Json::object([("program", path.into())]),
+    )]);
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(messages[0].get("success"), Some(&false.into()));
+    assert_eq!(
+        str_of(&messages[0], "message"),
+        format!("{path}: 3 byte memory access at 65534 is out of bounds")
+    );
+}
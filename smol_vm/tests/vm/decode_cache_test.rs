@@ -0,0 +1,93 @@
+use smol_vm::{syscall::Emulated, Register, Vm};
+
+use super::vm_with;
+
+/// Counts r1 through 256 values 3 times, counting the rounds in r2
+const COUNTING_LOOP: &[u8] = &[
+    // ALU Increment from Register
+    0b00_111_0_0_0,
+    // Register r1
+    0b0000_0001,
+    // ALU Equality from Immediate without writing the result
+    0b00_110_1_0_1,
+    // Register r1
+    0b0000_0001,
+    // Immediate 0
+    0,
+    // Branch if not equal 8 bit offset
+    0b11_010_0_00,
+    // Back to the increment of r1
+    -5_i8 as u8,
+    // ALU Increment from Register
+    0b00_111_0_0_0,
+    // Register r2
+    0b0000_0010,
+    // ALU Equality from Immediate without writing the result
+    0b00_110_1_0_1,
+    // Register r2
+    0b0000_0010,
+    // Immediate 3
+    3,
+    // Branch if not equal 8 bit offset
+    0b11_010_0_00,
+    // Back to the start
+    -12_i8 as u8,
+];
+
+#[test]
+pub fn it_runs_the_same_without_decode_cache() {
+    let mut cached = vm_with(Emulated, COUNTING_LOOP);
+    cached.run().unwrap();
+
+    let mut uncached = vm_with(Emulated, COUNTING_LOOP);
+    uncached.set_decode_cache(false);
+    uncached.run().unwrap();
+
+    assert_eq!(cached.registers[Register::R2], 3);
+    assert_eq!(cached.registers, uncached.registers);
+}
+
+#[test]
+pub fn it_decodes_again_after_writing_instructions() {
+    let mut vm = Vm::default();
+    vm.instructions.instructions = vec![
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r0
+        0b0000_0000,
+    ];
+    vm.run().unwrap();
+
+    // Same size so only the bytes tell the instructions apart
+    vm.instructions.instructions[1] = 0b0000_0001;
+    vm.registers.set(Register::Ic, 0);
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 1);
+    assert_eq!(vm.registers[Register::R1], 1);
+}
+
+#[test]
+pub fn it_decodes_again_after_loading_longer_instructions() {
+    let mut vm = Vm::default();
+    vm.instructions.instructions = vec![
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r0
+        0b0000_0000,
+    ];
+    vm.run().unwrap();
+
+    vm.instructions.instructions = vec![
+        // ALU Add Immediate
+        0b00_000_1_0_0,
+        // Register r0
+        0b0000_0000,
+        // Immediate 5
+        5,
+    ];
+    vm.registers.set(Register::Ic, 0);
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 6);
+}
@@ -0,0 +1,132 @@
+use smol_file::{SmolFile, Storage, StorageItem};
+use smol_vm::{ExitReason, Register, Vm, VmErrorKind};
+
+#[test]
+pub fn it_exits_at_end_of_instructions() {
+    let mut vm = Vm::default();
+    vm.instructions.instructions = vec![
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r0
+        0b0000_0000,
+    ];
+
+    assert_eq!(vm.run().unwrap(), ExitReason::EndOfInstructions);
+}
+
+#[test]
+pub fn it_rejects_unmapped_register() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::R0, 3);
+    vm.instructions.instructions = vec![
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r0
+        0b0000_0000,
+        // ALU Add from Register
+        0b00_000_0_0_0,
+        // Registers 0b1000 and r0
+        0b1000_0000,
+    ];
+    let err = vm.run().unwrap_err();
+
+    assert_eq!(err.kind, VmErrorKind::InvalidRegister(0b1000));
+    assert_eq!(err.ic, 2);
+    assert_eq!(err.opcode, Some(0b00_000_0_0_0));
+    assert_eq!(err.registers[Register::R0], 4);
+}
+
+#[test]
+pub fn it_rejects_truncated_instruction() {
+    let mut vm = Vm::default();
+    vm.instructions.instructions = vec![
+        // ALU Add from Immediate without the immediate
+        0b00_000_1_0_0,
+        // Register r0
+        0b0000_0000,
+    ];
+    let err = vm.run().unwrap_err();
+
+    assert_eq!(err.kind, VmErrorKind::TruncatedInstruction);
+    assert_eq!(err.ic, 0);
+}
+
+#[test]
+pub fn it_rejects_instruction_counter_out_of_range() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::Ic, 5);
+    vm.instructions.instructions = vec![
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r0
+        0b0000_0000,
+    ];
+    let err = vm.run().unwrap_err();
+
+    assert_eq!(err.kind, VmErrorKind::InstructionOutOfRange);
+    assert_eq!(err.opcode, None);
+}
+
+#[test]
+pub fn it_rejects_return_from_interrupt() {
+    let mut vm = Vm::default();
+    vm.instructions.instructions = vec![
+        // Return from interrupt
+        0b11_111_0_00,
+    ];
+    let err = vm.run().unwrap_err();
+
+    assert_eq!(err.kind, VmErrorKind::InvalidOpcode);
+}
+
+#[test]
+pub fn it_rejects_unknown_syscall() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::R0, 200);
+    vm.instructions.instructions = vec![
+        // Systemcall
+        0b11_101_111,
+    ];
+    let err = vm.run().unwrap_err();
+
+    assert_eq!(err.kind, VmErrorKind::UnknownSyscall(200));
+}
+
+#[test]
+pub fn it_rejects_instructions_past_64kib() {
+    let mut vm = Vm::default();
+    // ALU Increment from Register r0, repeated past what ic can address
+    vm.instructions.instructions = [0b00_111_0_0_0, 0b0000_0000].repeat(0x8001);
+    let err = vm.run().unwrap_err();
+
+    assert_eq!(err.kind, VmErrorKind::InstructionOutOfRange);
+    assert_eq!(err.ic, 0xfffe);
+    // 0x7fff increments wrap around
+    assert_eq!(vm.registers[Register::R0], 0xff);
+}
+
+#[test]
+pub fn it_rejects_variables_past_the_memory() {
+    let mut vm = Vm::default();
+    let file = SmolFile {
+        storage: Storage {
+            total_size: 7,
+            items: vec![StorageItem {
+                // Initialised flag and size of 3
+                size: 0x8003,
+                // Last 2 bytes of the memory
+                offset: u16::MAX - 2,
+                init_data: Some(b"hi\n".to_vec()),
+            }],
+        },
+        instructions: vec![],
+    };
+
+    assert_eq!(
+        vm.load_program(file),
+        Err(VmErrorKind::MemoryOutOfBounds {
+            address: (u16::MAX - 2) as usize,
+            size: 3
+        })
+    );
+}
@@ -0,0 +1,153 @@
+use std::io::{self, Cursor, Read, Write};
+
+use smol_vm::{gdb::GdbStub, syscall::Emulated, Register, Vm};
+
+use super::{vm_with, COUNTING};
+
+/// Connection that replays the packets from GDB and keeps the responses
+struct Connection {
+    input: Cursor<Vec<u8>>,
+    output: Vec<u8>,
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn packet(data: &str) -> String {
+    let checksum = data.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+    format!("${data}#{checksum:02x}")
+}
+
+/// Responses of the stub to the packets, without acknowledgements
+fn session(vm: Vm, packets: &[&str]) -> (GdbStub, Vec<String>) {
+    let mut stub = GdbStub::new(vm);
+    let input: String = packets.iter().map(|data| packet(data)).collect();
+    let mut conn = Connection {
+        input: Cursor::new(input.into_bytes()),
+        output: Vec::new(),
+    };
+    stub.serve(&mut conn).unwrap();
+
+    let output = String::from_utf8(conn.output).unwrap();
+    let responses = output
+        .split('$')
+        .skip(1)
+        .map(|response| {
+            let response = response.trim_end_matches('+');
+            let (data, _) = response.split_once('#').unwrap();
+            assert_eq!(packet(data), format!("${response}"));
+            data.to_string()
+        })
+        .collect();
+    (stub, responses)
+}
+
+#[test]
+pub fn it_reads_and_writes_registers() {
+    let mut vm = vm_with(Emulated, COUNTING);
+    vm.registers.set(Register::R1, 0xab);
+    vm.registers.set(Register::L0, 0x1234);
+    let (stub, responses) = session(vm, &["?", "g", "p8", "P2=7f", "Pa=0300", "p2"]);
+
+    assert_eq!(
+        responses,
+        [
+            "S05",
+            "00ab0000000000003412000000000000000000000000",
+            "3412",
+            "OK",
+            "OK",
+            "7f"
+        ]
+    );
+    assert_eq!(stub.vm.registers[Register::R2], 0x7f);
+    assert_eq!(stub.vm.registers[Register::Ic], 3);
+}
+
+#[test]
+pub fn it_reads_and_writes_memory() {
+    let (stub, responses) = session(
+        vm_with(Emulated, COUNTING),
+        &["m0,3", "M10002,2:beef", "m10001,4", "mffff,2"],
+    );
+
+    assert_eq!(responses, ["380080", "OK", "00beef00", "E14"]);
+    assert_eq!(stub.vm.stack.memory()[2..4], [0xbe, 0xef]);
+}
+
+#[test]
+pub fn it_steps_and_continues_to_breakpoints() {
+    let (stub, responses) = session(
+        vm_with(Emulated, COUNTING),
+        &["s", "p0", "Z0,4,1", "c", "p0", "z0,4,1", "c"],
+    );
+
+    assert_eq!(responses, ["S05", "01", "OK", "S05", "01", "OK", "W00"]);
+    assert_eq!(stub.vm.registers[Register::R0], 2);
+}
+
+#[test]
+pub fn it_stops_at_watchpoints() {
+    let (_, responses) = session(vm_with(Emulated, COUNTING), &["Z2,10000,1", "c"]);
+
+    assert_eq!(responses, ["OK", "T05watch:10000;"]);
+}
+
+#[test]
+pub fn it_reports_exit_codes_and_faults() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::R0, 60);
+    vm.registers.set(Register::R1, 3);
+    vm.instructions.instructions = vec![
+        // Systemcall
+        0b11_101_111,
+    ];
+    let (_, responses) = session(vm, &["c"]);
+    assert_eq!(responses, ["W03"]);
+
+    let mut vm = Vm::default();
+    vm.instructions.instructions = vec![
+        // Branch Return from interrupt
+        0b11_111_000,
+    ];
+    let (_, responses) = session(vm, &["s"]);
+    assert_eq!(responses, ["S04"]);
+}
+
+#[test]
+pub fn it_serves_the_target_description() {
+    let (_, responses) = session(
+        vm_with(Emulated, COUNTING),
+        &[
+            "qSupported:swbreak+",
+            "QStartNoAckMode",
+            "qXfer:features:read:target.xml:0,20",
+            "qXfer:features:read:target.xml:0,ffff",
+            "vMustReplyEmpty",
+            "k",
+            "?",
+        ],
+    );
+
+    assert_eq!(responses.len(), 5);
+    assert!(responses[0].contains("qXfer:features:read+"));
+    assert_eq!(responses[1], "OK");
+    assert!(responses[2].starts_with("m<?xml"));
+    assert!(responses[3].starts_with('l'));
+    assert!(responses[3].contains("<reg name=\"r0\" bitsize=\"8\""));
+    assert!(responses[3].contains("<reg name=\"ic\" bitsize=\"16\" type=\"code_ptr\""));
+    assert_eq!(responses[4], "");
+}
@@ -0,0 +1,140 @@
+use smol_vm::{syscall::Emulated, ExitReason, Register, Vm};
+
+use super::vm_with;
+
+/// Pushes r0, increments it, pushes it again and stores it into a variable
+const HISTORY: &[u8] = &[
+    // Stack Push 8-bit Register
+    0b10_00_00_00,
+    // Register r0
+    0b0000_0000,
+    // ALU Increment from Register
+    0b00_111_0_0_0,
+    // Register r3
+    0b0000_0011,
+    // ALU Increment from Register
+    0b00_111_0_0_0,
+    // Register r0
+    0b0000_0000,
+    // Stack Push 8-bit Register
+    0b10_00_00_00,
+    // Register r0
+    0b0000_0000,
+    // Store Byte imm16 r0
+    0b01_1_0_01_00,
+    0b0000_0000,
+    0x00,
+    0x90,
+    // ALU Increment from Register
+    0b00_111_0_0_0,
+    // Register r0
+    0b0000_0000,
+];
+
+#[test]
+pub fn it_steps_back() {
+    let mut vm = vm_with(Emulated, HISTORY);
+    vm.set_history(true);
+    vm.registers.set(Register::R0, 5);
+    vm.run().unwrap();
+    assert_eq!(vm.history().len(), 6);
+
+    let step = vm.step_back().unwrap();
+    assert_eq!(step.ic, 12);
+    assert_eq!(vm.registers[Register::Ic], 12);
+    assert_eq!(vm.registers[Register::R0], 6);
+
+    vm.step_back().unwrap();
+    assert_eq!(vm.stack.memory()[0x9000], 0);
+
+    vm.step_back().unwrap();
+    assert_eq!(vm.registers[Register::Sp], 1);
+    assert_eq!(vm.stack.memory()[1], 0);
+
+    while vm.step_back().is_some() {}
+    assert_eq!(vm.registers[Register::Ic], 0);
+    assert_eq!(vm.registers[Register::R0], 5);
+    assert_eq!(vm.registers[Register::R3], 0);
+    assert_eq!(vm.registers[Register::Sp], 0);
+    assert_eq!(vm.registers[Register::Fg], 0);
+    assert_eq!(vm.stack.memory()[0], 0);
+}
+
+#[test]
+pub fn it_replays_after_stepping_back() {
+    let mut vm = vm_with(Emulated, HISTORY);
+    vm.set_history(true);
+    vm.registers.set(Register::R0, 5);
+    vm.run().unwrap();
+    let registers = vm.registers.clone();
+    let memory = vm.stack.memory().to_vec();
+
+    while vm.step_back().is_some() {}
+    vm.run().unwrap();
+    assert_eq!(vm.registers, registers);
+    assert_eq!(vm.stack.memory(), memory);
+}
+
+#[test]
+pub fn it_runs_back_to_a_breakpoint() {
+    let mut vm = vm_with(Emulated, HISTORY);
+    vm.set_history(true);
+    vm.registers.set(Register::R0, 5);
+    vm.run().unwrap();
+
+    let step = vm.run_back(&[2, 6]).unwrap();
+    assert_eq!(step.ic, 6);
+    assert_eq!(vm.registers[Register::Ic], 6);
+    assert_eq!(vm.registers[Register::Sp], 1);
+
+    assert!(vm.run_back(&[10]).is_none());
+    assert_eq!(vm.registers[Register::Ic], 0);
+}
+
+#[test]
+pub fn it_goes_back_to_the_last_write() {
+    let mut vm = vm_with(Emulated, HISTORY);
+    vm.set_history(true);
+    vm.registers.set(Register::R0, 5);
+    vm.run().unwrap();
+
+    let step = vm.back_to_last_write(Register::R3).unwrap();
+    assert_eq!(step.ic, 2);
+    assert_eq!(vm.registers[Register::R3], 0);
+
+    let mut vm = vm_with(Emulated, HISTORY);
+    vm.set_history(true);
+    vm.registers.set(Register::R0, 5);
+    vm.run().unwrap();
+    let step = vm.back_to_last_memory_write(0x9000).unwrap();
+    assert_eq!(step.ic, 8);
+    assert_eq!(vm.registers[Register::Ic], 8);
+}
+
+#[test]
+pub fn it_undoes_exit() {
+    let mut vm = Vm::default();
+    vm.set_history(true);
+    vm.registers.set(Register::R0, 60);
+    vm.instructions.instructions = vec![
+        // Systemcall
+        0b11_101_111,
+    ];
+    assert_eq!(vm.run().unwrap(), ExitReason::Exit(0));
+
+    vm.step_back().unwrap();
+    assert!(!vm.is_finished());
+    assert_eq!(vm.exit_code(), None);
+}
+
+#[test]
+pub fn it_keeps_no_history_by_default() {
+    let mut vm = vm_with(Emulated, HISTORY);
+    vm.set_history(true);
+    vm.registers.set(Register::R0, 5);
+    vm.set_history(false);
+    vm.run().unwrap();
+
+    assert!(vm.history().is_empty());
+    assert!(vm.step_back().is_none());
+}
@@ -0,0 +1,72 @@
+use std::time::{Duration, Instant};
+
+use smol_vm::{syscall::Emulated, ExitReason, Register, Vm};
+
+use super::vm_with;
+
+/// Program that loops forever incrementing r0
+const INFINITE_LOOP: &[u8] = &[
+    // ALU Increment from Register
+    0b00_111_0_0_0,
+    // Register r0
+    0b0000_0000,
+    // Branch Jump 8-bit offset -2
+    0b11_000_000,
+    (-2i8) as u8,
+];
+
+#[test]
+pub fn it_runs_out_of_fuel() {
+    let mut vm = vm_with(Emulated, INFINITE_LOOP);
+    vm.set_fuel(Some(5));
+
+    assert_eq!(vm.run().unwrap(), ExitReason::OutOfFuel);
+    assert_eq!(vm.fuel(), Some(0));
+    assert_eq!(vm.registers[Register::R0], 3);
+    assert_eq!(vm.registers[Register::Ic], 2);
+}
+
+#[test]
+pub fn it_resumes_after_adding_fuel() {
+    let mut vm = vm_with(Emulated, INFINITE_LOOP);
+    vm.set_fuel(Some(4));
+    assert_eq!(vm.run().unwrap(), ExitReason::OutOfFuel);
+    assert_eq!(vm.registers[Register::R0], 2);
+    assert_eq!(vm.registers[Register::Ic], 0);
+
+    vm.add_fuel(4);
+    assert_eq!(vm.run().unwrap(), ExitReason::OutOfFuel);
+    assert_eq!(vm.registers[Register::R0], 4);
+    assert_eq!(vm.registers[Register::Ic], 0);
+}
+
+#[test]
+pub fn it_finishes_with_fuel_left() {
+    let mut vm = Vm::default();
+    vm.set_fuel(Some(2));
+    vm.instructions.instructions = vec![
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r0
+        0b0000_0000,
+    ];
+
+    assert_eq!(vm.run().unwrap(), ExitReason::EndOfInstructions);
+    assert_eq!(vm.fuel(), Some(1));
+}
+
+#[test]
+pub fn it_times_out() {
+    let mut vm = vm_with(Emulated, INFINITE_LOOP);
+    vm.set_deadline(Some(Instant::now() + Duration::from_millis(10)));
+
+    assert_eq!(vm.run().unwrap(), ExitReason::Timeout);
+    assert!(vm.registers[Register::R0] > 0);
+
+    // Resumes from the same ic once the deadline is moved
+    let ic = vm.registers[Register::Ic];
+    vm.set_deadline(None);
+    vm.set_fuel(Some(1));
+    assert_eq!(vm.run().unwrap(), ExitReason::OutOfFuel);
+    assert_ne!(vm.registers[Register::Ic], ic);
+}
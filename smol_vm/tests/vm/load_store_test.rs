@@ -0,0 +1,157 @@
+use smol_vm::{Register, Vm, VmErrorKind};
+
+#[test]
+pub fn it_loads_byte_relative_to_sp() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::Sp, 100);
+    vm.stack.memory_mut()[105] = 42;
+    vm.instructions.instructions = vec![
+        // Load byte sp + 8 bit immediate
+        0b01_0_0_00_00,
+        // Register r2
+        0b0000_0010,
+        // Offset of 5
+        5,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R2], 42);
+}
+
+#[test]
+pub fn it_stores_byte_relative_to_sp() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::Sp, 100);
+    vm.registers.set(Register::R2, 42);
+    vm.instructions.instructions = vec![
+        // Store byte sp + 8 bit immediate
+        0b01_1_0_00_00,
+        // Register r2
+        0b0000_0010,
+        // Offset of 5
+        5,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.stack.memory()[105], 42);
+}
+
+#[test]
+pub fn it_loads_word_from_immediate_address() {
+    let mut vm = Vm::default();
+    let address = (u16::MAX / 2) as usize + 256;
+    vm.stack.memory_mut()[address..address + 2].copy_from_slice(&[0x34, 0x12]);
+    vm.instructions.instructions = vec![
+        // Load word 16 bit immediate address
+        0b01_0_1_01_00,
+        // Register l1
+        0b0000_1010,
+        // Address 0x80ff in 16 bit little endian
+        0b1111_1111,
+        0b1000_0000,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::L1], 0x1234);
+}
+
+#[test]
+pub fn it_stores_word_to_immediate_address() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::L0, 0x1234);
+    vm.instructions.instructions = vec![
+        // Store word 16 bit immediate address
+        0b01_1_1_01_00,
+        // Register l0
+        0b0000_1001,
+        // Address 0x0100 in 16 bit little endian
+        0b0000_0000,
+        0b0000_0001,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.stack.memory()[0x100..0x102], [0x34, 0x12]);
+}
+
+#[test]
+pub fn it_loads_byte_relative_to_sp_register() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::Sp, 10);
+    vm.registers.set(Register::R1, 3);
+    vm.stack.memory_mut()[13] = 7;
+    vm.instructions.instructions = vec![
+        // Load byte sp + 8 bit register
+        0b01_0_0_10_00,
+        // Registers r1 and r0
+        0b0001_0000,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 7);
+}
+
+#[test]
+pub fn it_stores_through_16bit_pointer() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::L1, 40000);
+    vm.registers.set(Register::R4, 99);
+    vm.registers.set(Register::L0, 0xbeef);
+    vm.instructions.instructions = vec![
+        // Store byte 16 bit register address
+        0b01_1_0_11_00,
+        // Registers l1 and r4
+        0b1010_0100,
+        // Store word 16 bit register address
+        0b01_1_1_11_00,
+        // Registers l0 and l0
+        0b1001_1001,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.stack.memory()[40000], 99);
+    assert_eq!(vm.stack.memory()[0xbeef..0xbef1], [0xef, 0xbe]);
+}
+
+#[test]
+pub fn it_loads_through_16bit_pointer() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::L0, 40000);
+    vm.stack.memory_mut()[40000] = 5;
+    vm.stack.memory_mut()[40001] = 1;
+    vm.instructions.instructions = vec![
+        // Load byte 16 bit register address
+        0b01_0_0_11_00,
+        // Registers l0 and r7
+        0b1001_0111,
+        // Load word 16 bit register address
+        0b01_0_1_11_00,
+        // Registers l0 and l1
+        0b1001_1010,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R7], 5);
+    assert_eq!(vm.registers[Register::L1], 261);
+}
+
+#[test]
+pub fn it_rejects_access_outside_memory() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::L0, u16::MAX - 1);
+    vm.instructions.instructions = vec![
+        // Load word 16 bit register address
+        0b01_0_1_11_00,
+        // Registers l0 and l1
+        0b1001_1010,
+    ];
+    let err = vm.run().unwrap_err();
+
+    assert_eq!(
+        err.kind,
+        VmErrorKind::MemoryOutOfBounds {
+            address: (u16::MAX - 1) as usize,
+            size: 2
+        }
+    );
+    assert_eq!(err.ic, 0);
+}
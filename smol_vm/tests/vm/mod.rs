@@ -1,2 +1,55 @@
 mod alu_eq_test;
+mod branch_test;
+mod breakpoint_test;
+mod call_test;
+mod dap_test;
+mod decode_cache_test;
+mod error_test;
+mod gdb_test;
+mod history_test;
+mod limits_test;
+mod load_store_test;
+mod policy_test;
+mod record_test;
+mod registers_test;
+mod sandbox_test;
+mod snapshot_test;
 mod stack_test;
+mod step_test;
+mod syscall_test;
+mod trace_test;
+
+use smol_vm::{syscall::SyscallHandler, Vm};
+
+/// VM running `instructions` with `syscalls` as the backend of the system calls
+pub fn vm_with(syscalls: impl SyscallHandler + 'static, instructions: &[u8]) -> Vm {
+    let mut vm = Vm::with_syscalls(Box::new(syscalls));
+    vm.instructions.instructions = instructions.to_vec();
+    vm
+}
+
+/// Only a system call, the registers pick which one
+pub const SYSCALL: &[u8] = &[
+    // Systemcall
+    0b11_101_111,
+];
+
+/// Increments r0 and pushes it, twice
+pub const COUNTING: &[u8] = &[
+    // ALU Increment from Register
+    0b00_111_0_0_0,
+    // Register r0
+    0b0000_0000,
+    // Stack Push 8-bit Register
+    0b10_00_00_00,
+    // Register r0
+    0b0000_0000,
+    // ALU Increment from Register
+    0b00_111_0_0_0,
+    // Register r0
+    0b0000_0000,
+    // Stack Push 8-bit Register
+    0b10_00_00_00,
+    // Register r0
+    0b0000_0000,
+];
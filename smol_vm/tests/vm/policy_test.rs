@@ -0,0 +1,217 @@
+use smol_vm::{
+    syscall::{FdAccess, Policy, PolicyViolation, Sandbox, SyscallHandler, SyscallOutcome},
+    ExitReason, Register, Registers, Stack, Vm, VmErrorKind,
+};
+
+use super::{vm_with, SYSCALL};
+
+/// Answers every system call with the same result
+#[derive(Debug)]
+struct Answer(u8);
+
+impl SyscallHandler for Answer {
+    fn syscall(
+        &mut self,
+        registers: &mut Registers,
+        _stack: &mut Stack,
+    ) -> Result<SyscallOutcome, VmErrorKind> {
+        registers.set(Register::R0, self.0.into());
+        Ok(SyscallOutcome::Continue)
+    }
+}
+
+#[test]
+pub fn it_allows_listed_syscalls() {
+    let sandbox = Sandbox::new();
+    let mut vm = vm_with(
+        Policy::new(Box::new(sandbox.clone()))
+            .allow(1)
+            .allow_fd(1, FdAccess::Write),
+        SYSCALL,
+    );
+    vm.stack.memory_mut()[..3].copy_from_slice(b"hi\n");
+    vm.registers.set(Register::R0, 1);
+    vm.registers.set(Register::R1, 1);
+    vm.registers.set(Register::R3, 3);
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 3);
+    assert_eq!(sandbox.stdout(), b"hi\n");
+}
+
+#[test]
+pub fn it_denies_unlisted_syscalls() {
+    let sandbox = Sandbox::new();
+    let mut vm = vm_with(Policy::new(Box::new(sandbox.clone())).allow(60), SYSCALL);
+    vm.stack.memory_mut()[..3].copy_from_slice(b"hi\n");
+    vm.registers.set(Register::R0, 1);
+    vm.registers.set(Register::R1, 1);
+    vm.registers.set(Register::R3, 3);
+    let err = vm.run().unwrap_err();
+
+    assert_eq!(
+        err.kind,
+        VmErrorKind::SyscallDenied(PolicyViolation::Syscall(1))
+    );
+    assert_eq!(err.ic, 0);
+    assert!(sandbox.stdout().is_empty());
+}
+
+#[test]
+pub fn it_denies_file_descriptor_access() {
+    let sandbox = Sandbox::new();
+    let mut vm = vm_with(
+        Policy::new(Box::new(sandbox.clone()))
+            .allow(1)
+            .allow_fd(1, FdAccess::Write)
+            .allow_fd(2, FdAccess::Read),
+        SYSCALL,
+    );
+    vm.stack.memory_mut()[..3].copy_from_slice(b"hi\n");
+    vm.registers.set(Register::R0, 1);
+    vm.registers.set(Register::R1, 2);
+    vm.registers.set(Register::R3, 3);
+    let err = vm.run().unwrap_err();
+
+    assert_eq!(
+        err.kind,
+        VmErrorKind::SyscallDenied(PolicyViolation::FileDescriptor {
+            fd: 2,
+            access: FdAccess::Write
+        })
+    );
+    assert!(sandbox.stderr().is_empty());
+}
+
+#[test]
+pub fn it_limits_bytes_written() {
+    let sandbox = Sandbox::new();
+    let mut vm = vm_with(
+        Policy::new(Box::new(sandbox.clone()))
+            .allow(1)
+            .allow_fd(1, FdAccess::ReadWrite)
+            .max_bytes_written(4),
+        &[
+            // ALU Add Immediate r0 = 1
+            0b00_000_1_0_0,
+            0b0000_0000,
+            1,
+            // Systemcall
+            0b11_101_111,
+            // ALU Add Immediate r0 = 2 + 255
+            0b00_000_1_0_0,
+            0b0000_0000,
+            255,
+            // Systemcall
+            0b11_101_111,
+            // ALU Add Immediate r0 = 2 + 255
+            0b00_000_1_0_0,
+            0b0000_0000,
+            255,
+            // Systemcall
+            0b11_101_111,
+        ],
+    );
+    vm.stack.memory_mut()[..3].copy_from_slice(b"hi\n");
+    vm.registers.set(Register::R1, 1);
+    vm.registers.set(Register::R3, 2);
+    let err = vm.run().unwrap_err();
+
+    assert_eq!(
+        err.kind,
+        VmErrorKind::SyscallDenied(PolicyViolation::WriteLimit { limit: 4 })
+    );
+    assert_eq!(err.ic, 11);
+    assert_eq!(sandbox.stdout(), b"hihi");
+}
+
+#[test]
+pub fn it_charges_the_bytes_written() {
+    let sandbox = Sandbox::new();
+    let mut policy = Policy::new(Box::new(sandbox.clone()))
+        .allow(1)
+        .allow_fd(1, FdAccess::Write)
+        .allow_fd(3, FdAccess::Write)
+        .max_bytes_written(300);
+    let mut vm = Vm::default();
+
+    vm.registers.set(Register::R0, 1);
+    vm.registers.set(Register::R1, 1);
+    vm.registers.set(Register::R3, 200);
+    policy.syscall(&mut vm.registers, &mut vm.stack).unwrap();
+    assert_eq!(vm.registers[Register::R0], 200);
+    assert_eq!(policy.bytes_written(), 200);
+
+    // -EBADF from the sandbox isn't a count of bytes
+    vm.registers.set(Register::R0, 1);
+    vm.registers.set(Register::R1, 3);
+    vm.registers.set(Register::R3, 3);
+    policy.syscall(&mut vm.registers, &mut vm.stack).unwrap();
+    assert_eq!(vm.registers[Register::R0], 247);
+    assert_eq!(policy.bytes_written(), 200);
+    assert_eq!(sandbox.stdout().len(), 200);
+}
+
+#[test]
+pub fn it_only_allows_fds_opened_successfully() {
+    let opened = |result: u8| {
+        let mut policy = Policy::new(Box::new(Answer(result))).allow(1).allow(2);
+        let mut vm = Vm::default();
+        vm.registers.set(Register::R0, 2);
+        vm.registers.set(Register::L0, 1);
+        policy.syscall(&mut vm.registers, &mut vm.stack).unwrap();
+
+        vm.registers.set(Register::R0, 1);
+        vm.registers.set(Register::R1, result.into());
+        policy.syscall(&mut vm.registers, &mut vm.stack)
+    };
+
+    assert!(opened(100).is_ok());
+    // -ENOENT
+    assert_eq!(
+        opened(254),
+        Err(VmErrorKind::SyscallDenied(
+            PolicyViolation::FileDescriptor {
+                fd: 254,
+                access: FdAccess::Write
+            }
+        ))
+    );
+}
+
+#[test]
+pub fn it_only_closes_fds_the_program_opened() {
+    let mut policy = Policy::new(Box::new(Answer(0)))
+        .allow(2)
+        .allow(3)
+        .allow_fd(1, FdAccess::Write);
+    let mut vm = Vm::default();
+    vm.registers.set(Register::R0, 3);
+    vm.registers.set(Register::R1, 1);
+
+    assert_eq!(
+        policy.syscall(&mut vm.registers, &mut vm.stack),
+        Err(VmErrorKind::SyscallDenied(
+            PolicyViolation::UnknownFileDescriptor(1)
+        ))
+    );
+
+    // Opened as fd 0 since the inner handler always answers 0
+    vm.registers.set(Register::R0, 2);
+    policy.syscall(&mut vm.registers, &mut vm.stack).unwrap();
+    vm.registers.set(Register::R0, 3);
+    vm.registers.set(Register::R1, 0);
+    assert!(policy.syscall(&mut vm.registers, &mut vm.stack).is_ok());
+}
+
+#[test]
+pub fn it_contains_exit() {
+    let sandbox = Sandbox::new();
+    let mut vm = vm_with(Policy::new(Box::new(sandbox.clone())).allow(60), SYSCALL);
+    vm.registers.set(Register::R0, 60);
+    vm.registers.set(Register::R1, 7);
+
+    assert_eq!(vm.run().unwrap(), ExitReason::Exit(7));
+    // The exit never reached the inner handler
+    assert_eq!(sandbox.exit_code(), None);
+}
@@ -0,0 +1,90 @@
+use smol_vm::{
+    syscall::{Recorder, Recording, Replayer, Sandbox},
+    ExitReason, Register, Vm, VmErrorKind,
+};
+
+use super::vm_with;
+
+/// Reads r3 bytes from stdin, echoes them and exits with the first one
+const ECHO: &[u8] = &[
+    // Systemcall read(0, sp+0, 2)
+    0b11_101_111,
+    // ALU Increment from Register r1 = 1
+    0b00_111_0_0_0,
+    0b0000_0001,
+    // ALU Increment from Register r0 = 3
+    0b00_111_0_0_0,
+    0b0000_0000,
+    // ALU Sub Immediate r0 = 1
+    0b00_001_1_0_0,
+    0b0000_0000,
+    2,
+    // Systemcall write(1, sp+0, 2)
+    0b11_101_111,
+    // Load Byte sp+imm8 r1 = sp+0
+    0b01_0_0_00_00,
+    0b0000_0001,
+    0,
+    // ALU Add Immediate r0 = 60
+    0b00_000_1_0_0,
+    0b0000_0000,
+    58,
+    // Systemcall exit(r1)
+    0b11_101_111,
+];
+
+fn record_echo() -> (Vm, Recording) {
+    let sandbox = Sandbox::with_stdin(b"*!");
+    let recorder = Recorder::new(Box::new(sandbox.clone()));
+    let mut vm = vm_with(recorder.clone(), ECHO);
+    vm.registers.set(Register::R3, 2);
+
+    assert_eq!(vm.run().unwrap(), ExitReason::Exit(b'*'));
+    assert_eq!(sandbox.stdout(), b"*!");
+    (vm, recorder.recording())
+}
+
+#[test]
+pub fn it_records_syscalls() {
+    let (_, recording) = record_echo();
+
+    assert_eq!(recording.syscalls.len(), 3);
+    assert_eq!(recording.syscalls[0].before[Register::R0], 0);
+    assert_eq!(recording.syscalls[0].after[Register::R0], 2);
+    assert_eq!(recording.syscalls[0].writes, [(0, b"*!".to_vec())]);
+    assert!(recording.syscalls[1].writes.is_empty());
+    assert_eq!(recording.syscalls[2].exit_code, Some(b'*'));
+}
+
+#[test]
+pub fn it_replays_a_recording() {
+    let (recorded, recording) = record_echo();
+    let recording = Recording::from_bytes(&recording.to_bytes()).unwrap();
+
+    let mut vm = vm_with(Replayer::new(recording), ECHO);
+    vm.registers.set(Register::R3, 2);
+    assert_eq!(vm.run().unwrap(), ExitReason::Exit(b'*'));
+    assert_eq!(vm.registers, recorded.registers);
+    assert_eq!(vm.stack.memory(), recorded.stack.memory());
+}
+
+#[test]
+pub fn it_fails_when_the_replay_diverges() {
+    let (_, recording) = record_echo();
+
+    let mut vm = vm_with(Replayer::new(recording), ECHO);
+    vm.registers.set(Register::R3, 1);
+    let err = vm.run().unwrap_err();
+
+    assert_eq!(err.kind, VmErrorKind::ReplayMismatch { index: 0 });
+    assert_eq!(err.ic, 0);
+}
+
+#[test]
+pub fn it_fails_past_the_end_of_the_recording() {
+    let mut vm = vm_with(Replayer::new(Recording::default()), ECHO);
+    vm.registers.set(Register::R3, 2);
+    let err = vm.run().unwrap_err();
+
+    assert_eq!(err.kind, VmErrorKind::ReplayMismatch { index: 0 });
+}
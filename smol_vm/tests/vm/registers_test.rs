@@ -0,0 +1,46 @@
+use smol_vm::{flags, Register, Registers, Vm};
+
+#[test]
+pub fn it_indexes_registers_by_encoding() {
+    let mut registers = Registers::default();
+    for (value, register) in Register::ALL.into_iter().enumerate() {
+        assert_eq!(Register::from_encoding(register.encoding()), Some(register));
+        registers.set(register, 0x100 + value as u16);
+    }
+
+    assert_eq!(Register::from_encoding(0b1000), None);
+    assert_eq!(registers[Register::R7], 0x07);
+    assert_eq!(registers[Register::L0], 0x108);
+    assert_eq!(registers[Register::Sp], 0x10d);
+}
+
+#[test]
+pub fn it_truncates_8bit_registers() {
+    let mut registers = Registers::default();
+    registers.set(Register::R3, 0x1234);
+    registers.set(Register::L1, 0x1234);
+
+    assert_eq!(registers[Register::R3], 0x34);
+    assert_eq!(registers.get(Register::L1), 0x1234);
+}
+
+#[test]
+pub fn it_discards_writes_to_zr() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::Zr, 5);
+    vm.instructions.instructions = vec![
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register zr
+        0b0000_1111,
+        // ALU Add from Register
+        0b00_000_0_0_0,
+        // Registers zr and r0
+        0b1111_0000,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::Zr], 0);
+    assert_eq!(vm.registers[Register::R0], 0);
+    assert_eq!(vm.registers[Register::Fg], flags::ZERO);
+}
@@ -0,0 +1,105 @@
+use smol_vm::{syscall::Sandbox, ExitReason, Register, Vm};
+
+#[test]
+pub fn it_captures_stdout_and_stderr() {
+    let sandbox = Sandbox::new();
+    let mut vm = Vm::with_syscalls(Box::new(sandbox.clone()));
+    vm.stack.memory_mut()[..6].copy_from_slice(b"hi\nerr");
+    vm.instructions.instructions = vec![
+        // ALU Add Immediate r0 = 1
+        0b00_000_1_0_0,
+        0b0000_0000,
+        1,
+        // ALU Add Immediate r1 = 1
+        0b00_000_1_0_0,
+        0b0000_0001,
+        1,
+        // ALU Add Immediate r3 = 3
+        0b00_000_1_0_0,
+        0b0000_0011,
+        3,
+        // Systemcall
+        0b11_101_111,
+        // ALU Increment from Register r1 = 2
+        0b00_111_0_0_0,
+        0b0000_0001,
+        // ALU Add Immediate r2 = 3
+        0b00_000_1_0_0,
+        0b0000_0010,
+        3,
+        // ALU Xor r0 ^= r0
+        0b00_100_0_0_0,
+        0b0000_0000,
+        // ALU Increment from Register r0 = 1
+        0b00_111_0_0_0,
+        0b0000_0000,
+        // Systemcall
+        0b11_101_111,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(sandbox.stdout(), b"hi\n");
+    assert_eq!(sandbox.stderr(), b"err");
+    assert_eq!(vm.registers[Register::R0], 3);
+}
+
+#[test]
+pub fn it_reads_scripted_stdin() {
+    let sandbox = Sandbox::with_stdin(b"abc");
+    let mut vm = Vm::with_syscalls(Box::new(sandbox.clone()));
+    vm.registers.set(Register::R2, 4);
+    vm.registers.set(Register::R3, 2);
+    vm.instructions.instructions = vec![
+        // Systemcall
+        0b11_101_111,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 2);
+    assert_eq!(&vm.stack.memory()[4..7], b"ab\0");
+
+    sandbox.push_stdin(b"d");
+    let mut vm = Vm::with_syscalls(Box::new(sandbox));
+    vm.registers.set(Register::R3, 8);
+    vm.instructions.instructions = vec![
+        // Systemcall
+        0b11_101_111,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R0], 2);
+    assert_eq!(&vm.stack.memory()[..2], b"cd");
+}
+
+#[test]
+pub fn it_records_exit_code() {
+    let sandbox = Sandbox::new();
+    let mut vm = Vm::with_syscalls(Box::new(sandbox.clone()));
+    vm.registers.set(Register::R0, 60);
+    vm.registers.set(Register::R1, 3);
+    vm.instructions.instructions = vec![
+        // Systemcall
+        0b11_101_111,
+    ];
+
+    assert_eq!(vm.run().unwrap(), ExitReason::Exit(3));
+    assert_eq!(sandbox.exit_code(), Some(3));
+}
+
+#[test]
+pub fn it_rejects_unknown_file_descriptors() {
+    let sandbox = Sandbox::new();
+    let mut vm = Vm::with_syscalls(Box::new(sandbox.clone()));
+    vm.registers.set(Register::R0, 1);
+    vm.registers.set(Register::R1, 5);
+    vm.registers.set(Register::R3, 1);
+    vm.instructions.instructions = vec![
+        // Systemcall
+        0b11_101_111,
+    ];
+    vm.run().unwrap();
+
+    // -EBADF
+    assert_eq!(vm.registers[Register::R0], 247);
+    assert!(sandbox.stdout().is_empty());
+}
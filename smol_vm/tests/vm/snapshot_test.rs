@@ -0,0 +1,126 @@
+use smol_vm::{syscall::Emulated, ExitReason, Register, Snapshot, Vm};
+
+use super::{vm_with, COUNTING};
+
+fn assert_same_state(left: &Vm, right: &Vm) {
+    for register in Register::ALL {
+        assert_eq!(
+            left.registers.get(register),
+            right.registers.get(register),
+            "{register}"
+        );
+    }
+    assert_eq!(left.stack.memory(), right.stack.memory());
+    assert_eq!(
+        left.instructions.instructions,
+        right.instructions.instructions
+    );
+    assert_eq!(left.exit_code(), right.exit_code());
+}
+
+#[test]
+pub fn it_resumes_from_a_snapshot() {
+    let mut vm = vm_with(Emulated, COUNTING);
+    vm.registers.set(Register::L0, 0x1234);
+    vm.stack.memory_mut()[40000] = 0xaa;
+    vm.step().unwrap();
+    vm.step().unwrap();
+    let bytes = vm.snapshot().to_bytes();
+    vm.run().unwrap();
+
+    let mut restored = Vm::default();
+    restored
+        .restore(Snapshot::from_bytes(&bytes).unwrap())
+        .unwrap();
+    assert_eq!(restored.registers[Register::Ic], 4);
+    assert_eq!(restored.registers[Register::Sp], 1);
+
+    assert_eq!(restored.run().unwrap(), ExitReason::EndOfInstructions);
+    assert_same_state(&vm, &restored);
+    assert_eq!(restored.stack.memory()[..2], [1, 2]);
+}
+
+#[test]
+pub fn it_starts_the_history_over_on_restore() {
+    let mut vm = vm_with(Emulated, COUNTING);
+    vm.registers.set(Register::L0, 0x1234);
+    vm.stack.memory_mut()[40000] = 0xaa;
+    vm.set_history(true);
+    vm.step().unwrap();
+    vm.step().unwrap();
+    let snapshot = vm.snapshot();
+    vm.step().unwrap();
+
+    vm.restore(snapshot).unwrap();
+    assert!(vm.history().is_empty());
+    assert!(vm.step_back().is_none());
+    assert_eq!(vm.registers[Register::Ic], 4);
+    assert_eq!(vm.registers[Register::R0], 1);
+
+    vm.step().unwrap();
+    assert_eq!(vm.step_back().unwrap().ic, 4);
+    assert_eq!(vm.registers[Register::R0], 1);
+    assert!(vm.step_back().is_none());
+}
+
+#[test]
+pub fn it_round_trips_through_a_file() {
+    let mut vm = vm_with(Emulated, COUNTING);
+    vm.registers.set(Register::L0, 0x1234);
+    vm.stack.memory_mut()[40000] = 0xaa;
+    vm.registers.set(Register::R0, 60);
+    vm.registers.set(Register::R1, 9);
+    vm.instructions.instructions = vec![
+        // Systemcall
+        0b11_101_111,
+    ];
+    vm.run().unwrap();
+
+    let path = std::env::temp_dir().join(format!("smol_snapshot_{}", std::process::id()));
+    let path = path.to_str().unwrap();
+    vm.snapshot().save(path).unwrap();
+    let snapshot = Snapshot::load(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let mut restored = Vm::default();
+    restored.restore(snapshot).unwrap();
+    assert_same_state(&vm, &restored);
+    assert!(restored.is_finished());
+}
+
+#[test]
+pub fn it_rejects_a_snapshot_with_the_wrong_memory_size() {
+    let mut vm = vm_with(Emulated, COUNTING);
+    let mut snapshot = vm.snapshot();
+    snapshot.memory.truncate(100);
+    vm.registers.set(Register::L0, 0x1234);
+
+    let err = vm.restore(snapshot).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "snapshot memory is 100 bytes instead of 65535"
+    );
+    assert_eq!(vm.registers.get(Register::L0), 0x1234);
+}
+
+#[test]
+pub fn it_only_stores_used_memory_pages() {
+    let mut vm = vm_with(Emulated, COUNTING);
+    vm.registers.set(Register::L0, 0x1234);
+    vm.stack.memory_mut()[40000] = 0xaa;
+    let bytes = vm.snapshot().to_bytes();
+
+    // Header, registers, exit code, 1 page and the instructions
+    assert_eq!(bytes.len(), 9 + 22 + 2 + 2 + 2 + 256 + 4 + 8);
+}
+
+#[test]
+pub fn it_rejects_invalid_snapshots() {
+    let mut vm = vm_with(Emulated, COUNTING);
+    vm.registers.set(Register::L0, 0x1234);
+    vm.stack.memory_mut()[40000] = 0xaa;
+    let bytes = vm.snapshot().to_bytes();
+
+    assert!(Snapshot::from_bytes(b"not a snapshot").is_err());
+    assert!(Snapshot::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+}
@@ -1,4 +1,4 @@
-use smol_vm::Vm;
+use smol_vm::{Register, Vm, VmErrorKind};
 
 #[test]
 pub fn it_loads_immediate_variable_address() {
@@ -9,9 +9,9 @@ pub fn it_loads_immediate_variable_address() {
         // Value of 10
         10,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.sp, (u16::MAX / 2) + 10);
+    assert_eq!(vm.registers[Register::Sp], (u16::MAX / 2) + 10);
 }
 
 #[test]
@@ -24,45 +24,44 @@ pub fn it_loads_immediate_variable_address_16bit() {
         0b00000000,
         0b00000001,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.sp, (u16::MAX / 2) + 256);
+    assert_eq!(vm.registers[Register::Sp], (u16::MAX / 2) + 256);
 }
 
 #[test]
 pub fn it_loads_register_variable_address() {
     let mut vm = Vm::default();
-    vm.registers.r6 = 5;
+    vm.registers.set(Register::R6, 5);
     vm.instructions.instructions = vec![
         // Stack load variable regsiter
         0b10_10_0_0_00,
         // Register r6
         0b0000_0110,
     ];
-    vm.run();
+    vm.run().unwrap();
 
-    assert_eq!(vm.registers.sp, (u16::MAX / 2) + 5);
+    assert_eq!(vm.registers[Register::Sp], (u16::MAX / 2) + 5);
 }
 
 #[test]
 pub fn it_loads_16b_register_variable_address() {
     let mut vm = Vm::default();
-    let mut vm = Vm::default();
-    vm.registers.l1 = 700;
+    vm.registers.set(Register::L1, 700);
     vm.instructions.instructions = vec![
         // Stack load variable register 16 bit
         0b10_10_0_1_00,
         // Register l1
         0b0000_1010,
     ];
-    vm.run();
-    assert_eq!(vm.registers.sp, (u16::MAX / 2) + 700);
+    vm.run().unwrap();
+    assert_eq!(vm.registers[Register::Sp], (u16::MAX / 2) + 700);
 }
 
 #[test]
 pub fn it_resets_stackpointer() {
     let mut vm = Vm::default();
-    vm.registers.sp = 123;
+    vm.registers.set(Register::Sp, 123);
     // First make sure that the SP has been changed
     vm.instructions.instructions = vec![
         // Stack load variable immediate 8bit
@@ -70,15 +69,144 @@ pub fn it_resets_stackpointer() {
         // Value of 10
         10,
     ];
-    vm.run();
-    assert_eq!(vm.registers.sp, (u16::MAX / 2) + 10);
+    vm.run().unwrap();
+    assert_eq!(vm.registers[Register::Sp], (u16::MAX / 2) + 10);
 
-    vm.registers.ic = 0;
+    vm.registers.set(Register::Ic, 0);
     // Then make sure we actually reset it
     vm.instructions.instructions = vec![
         // Stack reset the variable pointer
         0b10_11_0_0_00,
     ];
-    vm.run();
-    assert_eq!(vm.registers.sp, 123);
+    vm.run().unwrap();
+    assert_eq!(vm.registers[Register::Sp], 123);
+}
+
+#[test]
+pub fn it_pushes_and_pops_8bit_register() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::R3, 42);
+    vm.instructions.instructions = vec![
+        // Stack push 8 bit register
+        0b10_00_0_0_00,
+        // Register r3
+        0b0000_0011,
+        // Stack pop 8 bit register
+        0b10_01_0_0_00,
+        // Register r5
+        0b0000_0101,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::R5], 42);
+    assert_eq!(vm.registers[Register::Sp], 0);
+    assert_eq!(vm.stack.stack()[0], 42);
+}
+
+#[test]
+pub fn it_pushes_16bit_register() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::L0, 0x1234);
+    vm.instructions.instructions = vec![
+        // Stack push 16 bit register
+        0b10_00_0_1_00,
+        // Register l0
+        0b0000_1001,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::Sp], 2);
+    assert_eq!(vm.stack.stack()[..2], [0x34, 0x12]);
+}
+
+#[test]
+pub fn it_pops_16bit_register() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::L0, 700);
+    vm.instructions.instructions = vec![
+        // Stack push 16 bit register
+        0b10_00_0_1_00,
+        // Register l0
+        0b0000_1001,
+        // Stack push 16 bit immediate
+        0b10_00_1_1_00,
+        // Value of 256 in 16 bit little endian
+        0b00000000,
+        0b00000001,
+        // Stack pop 16 bit register
+        0b10_01_0_1_00,
+        // Register l0
+        0b0000_1001,
+        // Stack pop 16 bit register
+        0b10_01_0_1_00,
+        // Register l1
+        0b0000_1010,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::L0], 256);
+    assert_eq!(vm.registers[Register::L1], 700);
+    assert_eq!(vm.registers[Register::Sp], 0);
+}
+
+#[test]
+pub fn it_pushes_8bit_immediate() {
+    let mut vm = Vm::default();
+    vm.instructions.instructions = vec![
+        // Stack push 8 bit immediate
+        0b10_00_1_0_00,
+        // Value of 10
+        10,
+    ];
+    vm.run().unwrap();
+
+    assert_eq!(vm.registers[Register::Sp], 1);
+    assert_eq!(vm.stack.stack()[0], 10);
+}
+
+#[test]
+pub fn it_detects_stack_underflow() {
+    let mut vm = Vm::default();
+    vm.instructions.instructions = vec![
+        // Stack pop 8 bit register
+        0b10_01_0_0_00,
+        // Register r0
+        0b0000_0000,
+    ];
+    let err = vm.run().unwrap_err();
+
+    assert_eq!(err.kind, VmErrorKind::StackUnderflow);
+    assert_eq!(err.ic, 0);
+}
+
+#[test]
+pub fn it_detects_stack_overflow() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::Sp, (u16::MAX / 2) - 1);
+    vm.instructions.instructions = vec![
+        // Stack push 16 bit register
+        0b10_00_0_1_00,
+        // Register l0
+        0b0000_1001,
+    ];
+    let err = vm.run().unwrap_err();
+
+    assert_eq!(err.kind, VmErrorKind::StackOverflow);
+    assert_eq!(err.ic, 0);
+}
+
+#[test]
+pub fn it_rejects_pop_into_immediate() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::Sp, 1);
+    vm.instructions.instructions = vec![
+        // Stack pop 8 bit immediate
+        0b10_01_1_0_00,
+        // Value of 10
+        10,
+    ];
+    let err = vm.run().unwrap_err();
+
+    assert_eq!(err.kind, VmErrorKind::InvalidOpcode);
+    assert_eq!(err.opcode, Some(0b10_01_1_0_00));
 }
@@ -0,0 +1,223 @@
+use smol_vm::{flags, MemoryAccess, Operand, Register, RegisterWrite, Vm};
+
+#[test]
+pub fn it_steps_one_instruction() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::R0, 1);
+    vm.registers.set(Register::R1, 2);
+    vm.instructions.instructions = vec![
+        // ALU Add from Register
+        0b00_000_0_0_0,
+        // Registers r0 and r1
+        0b0001_0000,
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r0
+        0b0000_0000,
+    ];
+    let step = vm.step().unwrap().unwrap();
+
+    assert_eq!(step.ic, 0);
+    assert_eq!(step.next_ic, 2);
+    assert_eq!(step.opcode, 0b00_000_0_0_0);
+    assert_eq!(step.mnemonic, "add");
+    assert_eq!(
+        step.operands,
+        [
+            Operand::Register(Register::R0),
+            Operand::Register(Register::R1)
+        ]
+    );
+    assert_eq!(
+        step.registers_written,
+        [RegisterWrite {
+            register: Register::R0,
+            old: 1,
+            new: 3,
+        }]
+    );
+    assert!(step.memory.is_empty());
+    assert_eq!(step.syscall, None);
+    assert_eq!(step.to_string(), "add r0 r1");
+    assert_eq!(vm.registers[Register::Ic], 2);
+    assert_eq!(vm.registers[Register::R0], 3);
+}
+
+#[test]
+pub fn it_returns_none_at_end() {
+    let mut vm = Vm::default();
+    vm.instructions.instructions = vec![
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r0
+        0b0000_0000,
+    ];
+
+    assert!(vm.step().unwrap().is_some());
+    assert!(vm.is_finished());
+    assert!(vm.step().unwrap().is_none());
+}
+
+#[test]
+pub fn it_describes_flag_writes() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::R2, 5);
+    vm.instructions.instructions = vec![
+        // ALU Equality from Immediate without writing the result
+        0b00_110_1_0_1,
+        // Register r2
+        0b0000_0010,
+        // Immediate 5
+        5,
+    ];
+    let step = vm.step().unwrap().unwrap();
+
+    assert_eq!(step.to_string(), "cmpi r2 5");
+    assert_eq!(
+        step.registers_written,
+        [RegisterWrite {
+            register: Register::Fg,
+            old: 0,
+            new: flags::ZERO,
+        }]
+    );
+}
+
+#[test]
+pub fn it_describes_stack_memory() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::L0, 0x1234);
+    vm.instructions.instructions = vec![
+        // Stack push 16 bit register
+        0b10_00_0_1_00,
+        // Register l0
+        0b0000_1001,
+        // Stack pop 8 bit register
+        0b10_01_0_0_00,
+        // Register r1
+        0b0000_0001,
+    ];
+    let push = vm.step().unwrap().unwrap();
+    let pop = vm.step().unwrap().unwrap();
+
+    assert_eq!(push.to_string(), "push l0");
+    assert_eq!(
+        push.memory,
+        [MemoryAccess::Write {
+            address: 0,
+            old: vec![0, 0],
+            new: vec![0x34, 0x12],
+        }]
+    );
+    assert_eq!(
+        push.registers_written,
+        [RegisterWrite {
+            register: Register::Sp,
+            old: 0,
+            new: 2,
+        }]
+    );
+    assert_eq!(pop.to_string(), "pop r1");
+    assert_eq!(
+        pop.memory,
+        [MemoryAccess::Read {
+            address: 1,
+            bytes: vec![0x12],
+        }]
+    );
+}
+
+#[test]
+pub fn it_describes_steps_after_running() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::L0, 0x1234);
+    vm.instructions.instructions = vec![
+        // Stack push 16 bit register
+        0b10_00_0_1_00,
+        // Register l0
+        0b0000_1001,
+        // Stack pop 8 bit register
+        0b10_01_0_0_00,
+        // Register r1
+        0b0000_0001,
+    ];
+    vm.set_fuel(Some(1));
+    vm.run().unwrap();
+    let pop = vm.step().unwrap().unwrap();
+
+    // Nothing of the push run without recording is left in the step
+    assert_eq!(pop.to_string(), "pop r1");
+    assert_eq!(
+        pop.memory,
+        [MemoryAccess::Read {
+            address: 1,
+            bytes: vec![0x12],
+        }]
+    );
+    assert_eq!(pop.registers_written.len(), 2);
+}
+
+#[test]
+pub fn it_describes_load_store_addresses() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::Sp, 10);
+    vm.registers.set(Register::R3, 9);
+    vm.instructions.instructions = vec![
+        // Store byte sp + 8 bit immediate
+        0b01_1_0_00_00,
+        // Register r3
+        0b0000_0011,
+        // Offset of 5
+        5,
+        // Load word 16 bit immediate address
+        0b01_0_1_01_00,
+        // Register l1
+        0b0000_1010,
+        // Address 15 in 16 bit little endian
+        15,
+        0,
+    ];
+    let store = vm.step().unwrap().unwrap();
+    let load = vm.step().unwrap().unwrap();
+
+    assert_eq!(store.to_string(), "stb r3 sp+5");
+    assert_eq!(
+        store.memory,
+        [MemoryAccess::Write {
+            address: 15,
+            old: vec![0],
+            new: vec![9],
+        }]
+    );
+    assert_eq!(load.to_string(), "ldw l1 15");
+    assert_eq!(
+        load.memory,
+        [MemoryAccess::Read {
+            address: 15,
+            bytes: vec![9, 0],
+        }]
+    );
+}
+
+#[test]
+pub fn it_describes_branches() {
+    let mut vm = Vm::default();
+    vm.instructions.instructions = vec![
+        // Branch if equal 8 bit offset
+        0b11_001_0_00,
+        // Offset of 2
+        2,
+        // Relative jump 16 bit offset
+        0b11_000_1_00,
+        // Offset of 3 in 16 bit little endian
+        3,
+        0,
+    ];
+    let branch = vm.step().unwrap().unwrap();
+    let jump = vm.step().unwrap().unwrap();
+
+    assert_eq!(branch.to_string(), "beq +2");
+    assert_eq!(branch.next_ic, 2);
+    assert_eq!(jump.to_string(), "jmp +3");
+    assert_eq!(jump.next_ic, 5);
+}
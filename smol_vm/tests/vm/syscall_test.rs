@@ -0,0 +1,176 @@
+use smol_vm::{ExitReason, Register, Vm};
+
+#[test]
+pub fn it_exits_through_syscall() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::R0, 60);
+    vm.registers.set(Register::R1, 42);
+    vm.instructions.instructions = vec![
+        // Systemcall
+        0b11_101_111,
+        // ALU Increment from Register
+        0b00_111_0_0_0,
+        // Register r7
+        0b0000_0111,
+    ];
+
+    assert_eq!(vm.run().unwrap(), ExitReason::Exit(42));
+    assert_eq!(vm.exit_code(), Some(42));
+    assert_eq!(vm.registers[Register::R7], 0);
+    assert!(vm.is_finished());
+    assert!(vm.step().unwrap().is_none());
+}
+
+#[test]
+pub fn it_writes_through_emulated_syscall() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::R0, 1);
+    vm.registers.set(Register::R1, 1);
+    vm.registers.set(Register::R3, 3);
+    vm.stack.memory_mut()[..3].copy_from_slice(b"hi\n");
+    vm.instructions.instructions = vec![
+        // Systemcall
+        0b11_101_111,
+    ];
+    let step = vm.step().unwrap().unwrap();
+
+    assert_eq!(step.to_string(), "syscall");
+    assert_eq!(step.syscall, Some(1));
+    assert_eq!(vm.registers[Register::R0], 3);
+}
+
+#[test]
+pub fn it_rejects_writes_outside_memory() {
+    let mut vm = Vm::default();
+    vm.registers.set(Register::R0, 1);
+    vm.registers.set(Register::R1, 1);
+    vm.registers.set(Register::R3, 10);
+    vm.registers.set(Register::Sp, u16::MAX - 4);
+    vm.instructions.instructions = vec![
+        // Systemcall
+        0b11_101_111,
+    ];
+    vm.run().unwrap();
+
+    // -EFAULT
+    assert_eq!(vm.registers[Register::R0], 242);
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+mod host {
+    use smol_vm::{
+        syscall::{x86_64_linux::vm_syscall, SyscallOutcome, X86_64Linux},
+        ExitReason, Register, Vm,
+    };
+
+    use crate::vm::{vm_with, SYSCALL};
+
+    #[test]
+    pub fn it_reads_from_an_opened_file() {
+        let mut vm = vm_with(X86_64Linux, SYSCALL);
+        vm.registers.set(Register::R0, 2);
+        vm.stack.memory_mut()[..10].copy_from_slice(b"/dev/zero\0");
+        vm.run().unwrap();
+        let fd = vm.registers[Register::R0];
+        assert!(fd < 128);
+
+        let mut vm = vm_with(X86_64Linux, SYSCALL);
+        vm.registers.set(Register::R1, fd);
+        vm.registers.set(Register::R2, 4);
+        vm.registers.set(Register::R3, 4);
+        vm.stack.memory_mut()[..9].fill(0xff);
+        vm.run().unwrap();
+
+        assert_eq!(vm.registers[Register::R0], 4);
+        assert_eq!(
+            vm.stack.memory()[..9],
+            [0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0, 0xff]
+        );
+
+        let mut vm = vm_with(X86_64Linux, SYSCALL);
+        vm.registers.set(Register::R0, 3);
+        vm.registers.set(Register::R1, fd);
+        vm.run().unwrap();
+
+        assert_eq!(vm.registers[Register::R0], 0);
+    }
+
+    #[test]
+    pub fn it_returns_host_errors() {
+        let mut vm = vm_with(X86_64Linux, SYSCALL);
+        vm.registers.set(Register::R0, 2);
+        vm.stack.memory_mut()[..12].copy_from_slice(b"/nonexistent");
+        vm.run().unwrap();
+
+        // -ENOENT
+        assert_eq!(vm.registers[Register::R0], 254);
+    }
+
+    #[test]
+    pub fn it_rejects_unterminated_paths() {
+        let mut vm = vm_with(X86_64Linux, SYSCALL);
+        vm.registers.set(Register::R0, 2);
+        vm.registers.set(Register::Sp, u16::MAX - 4);
+        vm.stack.memory_mut()[(u16::MAX - 4) as usize..].fill(b'a');
+        vm.run().unwrap();
+
+        // -EFAULT
+        assert_eq!(vm.registers[Register::R0], 242);
+    }
+
+    #[test]
+    pub fn it_rejects_reads_outside_memory() {
+        let mut vm = vm_with(X86_64Linux, SYSCALL);
+        vm.registers.set(Register::R0, 0);
+        vm.registers.set(Register::R3, 10);
+        vm.registers.set(Register::Sp, u16::MAX - 4);
+        vm.run().unwrap();
+
+        // -EFAULT
+        assert_eq!(vm.registers[Register::R0], 242);
+    }
+
+    #[test]
+    pub fn it_gets_the_pid() {
+        let mut vm = vm_with(X86_64Linux, SYSCALL);
+        vm.registers.set(Register::R0, 39);
+        vm.run().unwrap();
+
+        assert_eq!(vm.registers[Register::L0], std::process::id() as u16);
+        assert_eq!(vm.registers[Register::R0], std::process::id() as u8 as u16);
+    }
+
+    #[test]
+    pub fn it_stores_the_time() {
+        let mut vm = vm_with(X86_64Linux, SYSCALL);
+        vm.registers.set(Register::R0, 201);
+        vm.registers.set(Register::R1, 8);
+        vm.run().unwrap();
+
+        let bytes: [u8; 8] = vm.stack.memory()[8..16].try_into().unwrap();
+        let seconds = u64::from_le_bytes(bytes);
+        assert!(seconds > 1_600_000_000);
+        assert_eq!(vm.registers[Register::L0], seconds as u16);
+    }
+
+    #[test]
+    pub fn it_exits_the_vm_and_not_the_host() {
+        let mut vm = vm_with(X86_64Linux, SYSCALL);
+        vm.registers.set(Register::R0, 60);
+        vm.registers.set(Register::R1, 42);
+
+        assert_eq!(vm.run().unwrap(), ExitReason::Exit(42));
+        assert_eq!(vm.exit_code(), Some(42));
+    }
+
+    #[test]
+    pub fn it_exits_through_vm_syscall() {
+        let mut vm = Vm::default();
+        vm.registers.set(Register::R0, 60);
+        vm.registers.set(Register::R1, 7);
+
+        let outcome = vm_syscall(&mut vm.registers, &mut vm.stack).unwrap();
+        assert_eq!(outcome, SyscallOutcome::Exit(7));
+        assert_eq!(vm.registers[Register::R0], 60);
+    }
+}
@@ -0,0 +1,111 @@
+use std::{cell::RefCell, io::Write, rc::Rc};
+
+use smol_vm::{
+    syscall::{Sandbox, Tracer},
+    Register,
+};
+
+use super::{vm_with, SYSCALL};
+
+/// Log shared with the test
+#[derive(Clone, Default)]
+struct Log(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Log {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Log {
+    fn lines(&self) -> Vec<String> {
+        String::from_utf8(self.0.borrow().clone())
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Tracer writing into the log
+    fn tracer(&self, sandbox: Sandbox) -> Tracer {
+        Tracer::with_writer(Box::new(sandbox), Box::new(self.clone()))
+    }
+}
+
+#[test]
+pub fn it_traces_writes() {
+    let log = Log::default();
+    let mut vm = vm_with(log.tracer(Sandbox::new()), SYSCALL);
+    vm.registers.set(Register::R0, 1);
+    vm.registers.set(Register::R1, 1);
+    vm.registers.set(Register::R2, 1);
+    vm.registers.set(Register::R3, 3);
+    vm.stack.memory_mut()[..4].copy_from_slice(b"_hi\n");
+    vm.run().unwrap();
+
+    assert_eq!(log.lines(), [r#"write(1, "hi\n", 3) = 3"#]);
+}
+
+#[test]
+pub fn it_traces_writes_of_128_bytes_and_more() {
+    let log = Log::default();
+    let mut vm = vm_with(log.tracer(Sandbox::new()), SYSCALL);
+    vm.registers.set(Register::R0, 1);
+    vm.registers.set(Register::R1, 1);
+    vm.registers.set(Register::R3, 200);
+    vm.stack.memory_mut()[..200].fill(b'a');
+    vm.run().unwrap();
+
+    assert_eq!(
+        log.lines(),
+        [format!(r#"write(1, "{}"..., 200) = 200"#, "a".repeat(32))]
+    );
+}
+
+#[test]
+pub fn it_traces_read_buffers_after_the_call() {
+    let log = Log::default();
+    let mut vm = vm_with(log.tracer(Sandbox::with_stdin(b"ab")), SYSCALL);
+    vm.registers.set(Register::R3, 8);
+    vm.run().unwrap();
+
+    assert_eq!(log.lines(), [r#"read(0, "ab", 8) = 2"#]);
+}
+
+#[test]
+pub fn it_traces_errors() {
+    let log = Log::default();
+    let mut vm = vm_with(log.tracer(Sandbox::new()), SYSCALL);
+    vm.registers.set(Register::R0, 1);
+    vm.registers.set(Register::R1, 5);
+    vm.registers.set(Register::R3, 1);
+    vm.run().unwrap();
+
+    assert_eq!(log.lines(), [r#"write(5, "\x00", 1) = 247 (-9)"#]);
+}
+
+#[test]
+pub fn it_traces_exit_and_unknown_syscalls() {
+    let log = Log::default();
+    let mut vm = vm_with(log.tracer(Sandbox::new()), SYSCALL);
+    vm.registers.set(Register::R0, 60);
+    vm.registers.set(Register::R1, 4);
+    vm.run().unwrap();
+
+    let mut vm = vm_with(log.tracer(Sandbox::new()), SYSCALL);
+    vm.registers.set(Register::R0, 99);
+    vm.registers.set(Register::R6, 6);
+    vm.run().unwrap_err();
+
+    assert_eq!(
+        log.lines(),
+        [
+            "exit(4) = ?",
+            "syscall_99(0, 0, 0, 0, 0, 6) = ? (unknown system call 99)"
+        ]
+    );
+}